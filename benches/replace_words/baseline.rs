@@ -0,0 +1,142 @@
+//! `replace_words()` as it was before the rules were compiled once, for comparison.
+
+use regex::{Captures, Regex, Replacer};
+use std::borrow::Cow;
+use std::mem::swap;
+
+pub fn replace_words(s: &str) -> String {
+    let no_warnings = !s.contains("warning:") && !s.contains("error:");
+
+    let mut result = s.to_string();
+    regex_replace_once(&mut result, "^    Checking(.*)", "I'm checking$1...");
+
+    regex_replace_once(
+        &mut result,
+        r"(?m)^error: aborting due to previous error.*",
+        "Sorry, but I cannot continue compiling with that error.",
+    );
+
+    regex_replace_once(
+        &mut result,
+        r"(?m)^error: aborting due to \d* previous errors; \d* warnings emitted",
+        "Sorry, but you have too many errors in your code.",
+    );
+
+    regex_replace_once(
+        &mut result,
+        r"(?m)^error: could not compile (.*) due to.*",
+        "Let's fix $1!",
+    );
+
+    regex_replace_once(
+        &mut result,
+        "    Finished(.*)\n?$",
+        "I finished compiling$1.\n",
+    );
+
+    regex_replace(
+        &mut result,
+        r"(?m)^error: expected (.*), found (.*)",
+        "The syntax is wrong because I expected $1 but I found $2.",
+    );
+
+    regex_replace(
+        &mut result,
+        r"(?m)^error\[E0597\]: `(.*)` does not live long enough$",
+        "Oops! It looks like the variable with lifetime `$1` is dropped before it is used.",
+    );
+
+    regex_replace(
+        &mut result,
+        r"(?m)^error\[\S+\]: expected (.*), found (.*)",
+        "Oops! I expected $1, but I found $2.",
+    );
+
+    regex_replace(&mut result, r"(?m)^error\[\S+\]:(.*)", "Oops!$1.");
+
+    regex_replace(&mut result, r"(?m)^help: use (.*)", "Psst... use $1.");
+
+    regex_replace(
+        &mut result,
+        r"(?m)^(warning|error):(.*)",
+        |caps: &Captures| {
+            if let Some(s) = caps[2].strip_suffix(" warnings emitted") {
+                "You have".to_string() + s + " issues in your code."
+            } else if let Some(s) = caps[2].strip_suffix(" warning emitted") {
+                "You have".to_string() + s + " issue in your code."
+            } else if caps[2].contains('.') {
+                "It looks like this could be improved because".to_string() + &caps[2] + "."
+            } else {
+                "Hmmm...".to_string() + &caps[2] + "."
+            }
+        },
+    );
+
+    regex_replace(&mut result, r"(?m)^(  = )?note:(.*)", |caps: &Captures| {
+        let mut result = if caps[0].starts_with("  ") {
+            "  Note:"
+        } else {
+            "Note:"
+        }
+        .to_string();
+        result.push_str(&caps[2]);
+        if !caps[2].ends_with('.') && !caps[2].ends_with('?') {
+            result.push('.')
+        }
+        result
+    });
+
+    regex_replace(
+        &mut result,
+        r"(?m)^  = help: for further information visit (.*)",
+        "  Would you like some help with this? Visit\n  $1.",
+    );
+
+    regex_replace(&mut result, r"(?m)^  = help:(.*)", |caps: &Captures| {
+        let mut result = "  Hint:".to_string() + &caps[1];
+        if !caps[1].ends_with('?') {
+            result.push('.')
+        }
+        result
+    });
+
+    // "^^ help: if this is intentional..."
+    regex_replace(&mut result, r"(?m)^  \|( *)(\^+) help: if", "  |$1$2 If");
+
+    // "^ help: remove this semicolon"
+    regex_replace(
+        &mut result,
+        r"(?m)^  \|( *| \|_+)(\^+) help:",
+        "  |$1$2 You should",
+    );
+
+    regex_replace(
+        &mut result,
+        r"thread 'main' panicked at ",
+        "#$@#$@#$!#$%!@#$ !INTERNAL ERROR! PLEASE REFER TO OWNERS MANUAL\n",
+    );
+
+    if no_warnings {
+        result.push_str("Woohoo, no warnings!\n");
+    }
+
+    result
+}
+
+fn regex_replace<R>(str: &mut String, regex: &str, replacement: R)
+where
+    R: Replacer,
+{
+    if let Cow::Owned(mut s) = Regex::new(regex).unwrap().replace_all(str, replacement) {
+        swap(str, &mut s);
+    }
+}
+
+fn regex_replace_once<R>(str: &mut String, regex: &str, replacement: R)
+where
+    R: Replacer,
+{
+    if let Cow::Owned(mut s) = Regex::new(regex).unwrap().replace(str, replacement) {
+        swap(str, &mut s);
+    }
+}
@@ -0,0 +1,78 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::fmt::Write;
+
+mod baseline;
+
+/// Generates the output of a clippy run on a large project with `diagnostic_count` diagnostics.
+fn generate_input(diagnostic_count: usize) -> String {
+    let mut result = "    Checking playground v0.0.1 (/playground)\n".to_string();
+    for i in 0..diagnostic_count {
+        let line = i + 1;
+        match i % 3 {
+            0 => write!(
+                result,
+                r#"warning: unused variable: `pi`
+ --> src/main.rs:{line}:9
+  |
+{line} |     let pi = 3.14;
+  |         ^^ help: if this is intentional, prefix it with an underscore: `_pi`
+  |
+  = note: `#[warn(unused_variables)]` on by default
+
+"#
+            ),
+            1 => write!(
+                result,
+                r#"error: approximate value of `f{{32, 64}}::consts::PI` found
+ --> src/main.rs:{line}:14
+  |
+{line} |     let pi = 3.14;
+  |              ^^^^
+  |
+  = help: consider using the constant directly
+  = help: for further information visit https://rust-lang.github.io/rust-clippy/master/index.html#approx_constant
+  = note: `#[deny(clippy::approx_constant)]` on by default
+
+"#
+            ),
+            _ => write!(
+                result,
+                r#"error[E0597]: `a` does not live long enough
+ --> src/main.rs:{line}:13
+  |
+{line} |         b = &a;
+  |             ^^ borrowed value does not live long enough
+
+For more information about this error, try `rustc --explain E0597`.
+"#
+            ),
+        }
+        .unwrap();
+    }
+    result.push_str(
+        "error: could not compile `playground` (bin \"playground\") due to previous error\n",
+    );
+    result
+}
+
+fn replace_words(c: &mut Criterion) {
+    let mut group = c.benchmark_group("replace_words");
+    for diagnostic_count in [100, 1_000, 10_000] {
+        let input = generate_input(diagnostic_count);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("baseline", diagnostic_count),
+            &input,
+            |b, input| b.iter(|| baseline::replace_words(input)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("rules", diagnostic_count),
+            &input,
+            |b, input| b.iter(|| clippit::replace_words(input)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, replace_words);
+criterion_main!(benches);
@@ -0,0 +1,128 @@
+//! Generates `src/lints.toml` from the documentation of the installed clippy, so that clippit can
+//! explain clippy's lints without an internet connection.
+//!
+//! ```shell
+//! cargo run --example generate_lints > src/lints.toml
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::process::Command;
+
+#[derive(Serialize)]
+struct Lint {
+    what: String,
+    why: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    example: String,
+}
+
+fn main() -> Result<()> {
+    let mut lints = BTreeMap::new();
+    for name in lint_names()? {
+        let output = Command::new("cargo")
+            .args(["clippy", "--explain", &name])
+            .output()
+            .with_context(|| format!("could not run cargo clippy --explain {name}"))?;
+        let doc = String::from_utf8(output.stdout)?;
+        let lint = parse_doc(&doc);
+        // Lint groups such as `clippy::pedantic` have no documentation
+        if !lint.what.is_empty() {
+            lints.insert(name, lint);
+        }
+    }
+
+    let version = Command::new("cargo")
+        .args(["clippy", "--version"])
+        .output()?;
+    print!(
+        "# Generated by `cargo run --example generate_lints` from {}\n\n{}",
+        String::from_utf8(version.stdout)?.trim(),
+        toml::to_string_pretty(&lints)?
+    );
+    Ok(())
+}
+
+/// Returns the names of clippy's lints without `clippy::`, e.g. `double_parens`.
+fn lint_names() -> Result<Vec<String>> {
+    let output = Command::new("clippy-driver")
+        .args(["-W", "help"])
+        .output()
+        .context("could not run clippy-driver")?;
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix("clippy::"))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(|name| name.replace('-', "_"))
+        .collect())
+}
+
+/// Takes the sections that explain the lint from its markdown documentation.
+fn parse_doc(doc: &str) -> Lint {
+    let mut lint = Lint {
+        what: String::new(),
+        why: String::new(),
+        example: String::new(),
+    };
+    for section in doc.split("### ").skip(1) {
+        let (heading, body) = section.split_once('\n').unwrap_or((section, ""));
+        let field = match heading.trim().trim_end_matches(['.', ':', '?']) {
+            "What it does" => &mut lint.what,
+            "Why is this bad" | "Why restrict this" | "Why is this better" => &mut lint.why,
+            "Example" | "Examples" => &mut lint.example,
+            _ => continue,
+        };
+        *field = plain_text(body);
+    }
+    lint
+}
+
+/// Joins the lines of each paragraph so that the speech bubble can wrap them. Code blocks keep
+/// their lines and are indented instead of fenced, and the lines that rustdoc hides are left out.
+fn plain_text(markdown: &str) -> String {
+    let mut result = String::new();
+    let mut code_language = None;
+    for line in markdown.lines() {
+        if let Some(language) = line.trim_start().strip_prefix("```") {
+            code_language = match code_language {
+                Some(_) => None,
+                None => Some(language.to_string()),
+            };
+            if !result.is_empty() && !result.ends_with('\n') {
+                result.push('\n');
+            }
+            continue;
+        }
+        match &code_language {
+            Some(language) => {
+                let is_rust = language.is_empty() || language.starts_with("rust");
+                if is_rust && (line.trim() == "#" || line.trim_start().starts_with("# ")) {
+                    continue;
+                }
+                if !line.trim().is_empty() {
+                    result.push_str("    ");
+                    result.push_str(line);
+                }
+                result.push('\n');
+            }
+            None if line.trim().is_empty() => {
+                if !result.is_empty() && !result.ends_with("\n\n") {
+                    if !result.ends_with('\n') {
+                        result.push('\n');
+                    }
+                    result.push('\n');
+                }
+            }
+            None => {
+                let is_list_item =
+                    line.starts_with(['*', '-']) || line.starts_with(char::is_numeric);
+                if !result.is_empty() && !result.ends_with('\n') {
+                    result.push(if is_list_item { '\n' } else { ' ' });
+                }
+                result.push_str(line.trim());
+            }
+        }
+    }
+    result.trim_end().to_string() + "\n"
+}
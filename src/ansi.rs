@@ -0,0 +1,162 @@
+//! ANSI escape sequences in colored output, e.g. from `cargo clippy --color=always`.
+//!
+//! Escape sequences take up no columns in the terminal. SGR sequences such as `\x1b[1;33m` set the
+//! colors and the style of the text after them, until they are reset by `\x1b[0m`.
+
+use std::borrow::Cow;
+
+/// Resets colors and styles to the terminal's default.
+pub const RESET: &str = "\x1b[0m";
+
+/// A part of a string that is either one escape sequence or text without escape sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment<'a> {
+    Escape(&'a str),
+    Text(&'a str),
+}
+
+/// Splits `s` into escape sequences and text.
+pub fn segments(s: &str) -> impl Iterator<Item = Segment<'_>> {
+    let mut rest = s;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let (segment, len) = match escape_len(rest) {
+            Some(len) => (Segment::Escape(&rest[..len]), len),
+            None => {
+                // An incomplete escape sequence is text
+                let start = usize::from(rest.starts_with('\x1b'));
+                let len = rest[start..].find('\x1b').map_or(rest.len(), |i| i + start);
+                (Segment::Text(&rest[..len]), len)
+            }
+        };
+        rest = &rest[len..];
+        Some(segment)
+    })
+}
+
+/// Returns the length of the escape sequence at the start of `s`, if there is one.
+fn escape_len(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    if bytes.first() != Some(&0x1b) {
+        return None;
+    }
+    match bytes.get(1)? {
+        // CSI: parameter and intermediate bytes, then a final byte such as `m`
+        b'[' => {
+            let end = bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b))?;
+            Some(end + 3)
+        }
+        // Other sequences, e.g. `\x1b(B`, are at most one more byte, which is ASCII. A stray
+        // escape before other text must not split a character.
+        b'(' | b')' => match bytes.get(2)? {
+            b if b.is_ascii() => Some(3),
+            _ => Some(2),
+        },
+        b if b.is_ascii() => Some(2),
+        _ => None,
+    }
+}
+
+/// Returns `s` without escape sequences.
+pub fn strip(s: &str) -> Cow<'_, str> {
+    if !s.contains('\x1b') {
+        return Cow::Borrowed(s);
+    }
+    Cow::Owned(
+        segments(s)
+            .filter_map(|segment| match segment {
+                Segment::Text(text) => Some(text),
+                Segment::Escape(_) => None,
+            })
+            .collect(),
+    )
+}
+
+/// The SGR sequences that are in effect since the last reset.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Style(String);
+
+impl Style {
+    /// Applies an escape sequence. Sequences other than SGR sequences do not change the style.
+    pub fn push(&mut self, escape: &str) {
+        if escape == RESET || escape == "\x1b[m" {
+            self.0.clear();
+        } else if escape.starts_with("\x1b[") && escape.ends_with('m') {
+            self.0.push_str(escape);
+        }
+    }
+
+    /// Returns the escape sequences that restore this style after a reset.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Gives `rewritten`, which was rewritten from `original` without its escape sequences, the style
+/// of the first character of `original`.
+pub fn restyle(original: &str, rewritten: &str) -> String {
+    let mut style = Style::default();
+    for segment in segments(original) {
+        match segment {
+            Segment::Escape(escape) => style.push(escape),
+            Segment::Text(_) => break,
+        }
+    }
+    if style.is_empty() {
+        rewritten.to_string()
+    } else {
+        format!("{}{rewritten}{RESET}", style.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("", &[])]
+    #[case("warning", &[Segment::Text("warning")])]
+    #[case("你\x1b[0m", &[Segment::Text("你"), Segment::Escape("\x1b[0m")])]
+    #[case(
+        "\x1b[1m\x1b[33mwarning\x1b[0m: a",
+        &[
+            Segment::Escape("\x1b[1m"),
+            Segment::Escape("\x1b[33m"),
+            Segment::Text("warning"),
+            Segment::Escape("\x1b[0m"),
+            Segment::Text(": a"),
+        ]
+    )]
+    #[case("a\x1b[38;5;12m|", &[Segment::Text("a"), Segment::Escape("\x1b[38;5;12m"), Segment::Text("|")])]
+    #[case("\x1b(Ba", &[Segment::Escape("\x1b(B"), Segment::Text("a")])]
+    #[case("a\x1b[1", &[Segment::Text("a"), Segment::Text("\x1b[1")])]
+    #[case("\x1b(é", &[Segment::Escape("\x1b("), Segment::Text("é")])]
+    fn test_segments(#[case] input: &str, #[case] expected: &[Segment]) {
+        assert_eq!(segments(input).collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_strip() {
+        assert_eq!(strip("\x1b[1m\x1b[33mwarning\x1b[0m: a"), "warning: a");
+        assert!(matches!(strip("warning: a"), Cow::Borrowed(_)));
+        assert_eq!(strip("warning: unused \x1b(é x"), "warning: unused é x");
+    }
+
+    #[rstest]
+    #[case("warning: a", "Hmmm... a.", "Hmmm... a.")]
+    #[case(
+        "\x1b[0m\x1b[1m\x1b[33mwarning\x1b[0m\x1b[1m: a\x1b[0m",
+        "Hmmm... a.",
+        "\x1b[1m\x1b[33mHmmm... a.\x1b[0m"
+    )]
+    fn test_restyle(#[case] original: &str, #[case] rewritten: &str, #[case] expected: &str) {
+        assert_eq!(restyle(original, rewritten), expected);
+    }
+}
@@ -0,0 +1,175 @@
+//! Assistants that say the output in a speech bubble.
+//!
+//! The built-in characters are in `src/characters.toml`, which also describes the format. Other
+//! characters can be loaded from a file with the same format.
+
+use crate::rules::configured_setting;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+const BUILTIN_CHARACTERS: &str = include_str!("characters.toml");
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+pub struct Character {
+    #[serde(default)]
+    pub name: String,
+    /// Drawn above the speech bubble. The last line is the tail of the speech bubble.
+    pub art: String,
+    /// Column of the speech bubble's top border where the tail attaches.
+    #[serde(default)]
+    pub tail: Option<u16>,
+    #[serde(default)]
+    pub bubble: BubbleStyle,
+}
+
+impl Character {
+    /// Returns the column where the tail attaches, which is at least 1 so that the top border
+    /// starts with its corner.
+    pub fn tail_column(&self) -> u16 {
+        let column = self.tail.unwrap_or_else(|| {
+            let last_line = self.art.lines().last().unwrap_or_default();
+            let indent = last_line.len() - last_line.trim_start_matches(' ').len();
+            u16::try_from(indent).unwrap_or(u16::MAX)
+        });
+        column.max(1)
+    }
+}
+
+impl Default for Character {
+    /// Returns Clippit.
+    fn default() -> Self {
+        builtin_characters().swap_remove(0)
+    }
+}
+
+/// Border of the speech bubble.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BubbleStyle {
+    /// `/‾‾‾\`, `|` and `\___/`
+    #[default]
+    Classic,
+    /// Box-drawing characters with rounded corners
+    Round,
+    /// `.---.`, `|` and `'---'`
+    Ascii,
+}
+
+/// The characters that a speech bubble is drawn with.
+pub struct BubbleChars {
+    pub top_left: char,
+    pub top: char,
+    pub top_right: char,
+    pub side: char,
+    pub bottom_left: char,
+    pub bottom: char,
+    pub bottom_right: char,
+}
+
+impl BubbleStyle {
+    pub fn chars(self) -> BubbleChars {
+        let [top_left, top, top_right, side, bottom_left, bottom, bottom_right] = match self {
+            BubbleStyle::Classic => ['/', '‾', '\\', '|', '\\', '_', '/'],
+            BubbleStyle::Round => ['╭', '─', '╮', '│', '╰', '─', '╯'],
+            BubbleStyle::Ascii => ['.', '-', '.', '|', '\'', '-', '\''],
+        };
+        BubbleChars {
+            top_left,
+            top,
+            top_right,
+            side,
+            bottom_left,
+            bottom,
+            bottom_right,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CharacterFile {
+    characters: Vec<Character>,
+}
+
+/// Returns the built-in characters. The first one is Clippit.
+pub fn builtin_characters() -> Vec<Character> {
+    toml::from_str::<CharacterFile>(BUILTIN_CHARACTERS)
+        .unwrap()
+        .characters
+}
+
+/// Returns the built-in character called `name`, or else the character in the file at `name`.
+pub fn load_character(name: &str) -> Result<Character> {
+    if let Some(character) = builtin_characters().into_iter().find(|c| c.name == name) {
+        return Ok(character);
+    }
+    let s = std::fs::read_to_string(name)
+        .with_context(|| format!("unknown character: {name} is neither built in nor a file"))?;
+    toml::from_str(&s).with_context(|| format!("invalid character file {name}"))
+}
+
+/// Returns the character set with `character = "..."` in the rule files from `rule_file_paths()`.
+/// A later file overrides an earlier one.
+pub fn configured_character(config: Option<&Path>) -> Result<Option<String>> {
+    configured_setting(config, "character")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_characters() {
+        let characters = builtin_characters();
+        let names: Vec<&str> = characters.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["clippit", "rover", "merlin", "links", "f1", "office"]
+        );
+        for character in &characters {
+            // The tail is drawn with the top border's gap under it
+            assert_eq!(character.tail_column(), 5, "{}", character.name);
+            assert!(character.art.ends_with("/\\\n"), "{}", character.name);
+        }
+    }
+
+    #[test]
+    fn test_load_character() {
+        assert_eq!(load_character("rover").unwrap().name, "rover");
+        assert!(load_character("bob").is_err());
+
+        let character: Character = toml::from_str(
+            r#"
+art = '''
+ o
+/|\
+ /\
+'''
+bubble = "round"
+"#,
+        )
+        .unwrap();
+        assert_eq!(character.tail_column(), 1);
+        assert_eq!(character.bubble, BubbleStyle::Round);
+    }
+
+    #[test]
+    fn test_configured_character() {
+        // Each test has a directory of its own, which is removed when it is dropped
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clippit.toml");
+        std::fs::write(
+            &path,
+            r#"
+character = "merlin"
+
+[[rules]]
+id = "warning"
+enabled = false
+"#,
+        )
+        .unwrap();
+        let character = configured_character(Some(&path)).unwrap();
+        assert_eq!(character.as_deref(), Some("merlin"));
+    }
+}
@@ -1,17 +1,11 @@
+use crate::ansi::{self, Segment, Style};
+use crate::character::{BubbleStyle, Character};
 use std::mem::take;
-use textwrap::wrap;
-
-const CLIPPY_ART: &str = r#"   /‾‾\
-   |  |
-   @  @
-   || |/
-   || ||
-   |\_/|
-   \___/
-     /\
-"#;
-
-/// Inputs a string and outputs ascii art of Clippy saying the text.
+use textwrap::{wrap, Options};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Inputs a string and outputs ascii art of a character such as Clippy saying the text.
 ///
 /// Call `add_str()` to input strings and call `finish()` at the end after all text as been added.
 /// `ClippyArt` implements `Iterator` to return the output string.
@@ -19,111 +13,315 @@ const CLIPPY_ART: &str = r#"   /‾‾\
 pub struct ClippyArt {
     buf: String,
 
+    // Width of the speech bubble including its borders
     output_width: u16,
 
+    bubble: BubbleStyle,
+
     // Incomplete last line without vertical bars
     line: String,
 
-    // Length of line in chars. Since this is the number of characters, the displayed width may be
-    // different. For example, combining characters will cause lines to appear shorter.
-    line_char_length: u16,
+    // Display width of line in terminal columns. Wide characters such as CJK take up two columns,
+    // and combining characters, zero-width joiners and escape sequences take up none.
+    line_width: u16,
+
+    // Colors at the end of line, which continue on the next line
+    style: Style,
 }
 
-const PREFIX: &str = "/‾‾‾‾  ";
+/// The speech bubble is at least wide enough for 4 columns of text.
+const MIN_WIDTH: u16 = 8;
+
+/// Labels of snippet lines are wrapped next to the markers if there are this many columns left.
+const MIN_LABEL_WIDTH: u16 = 8;
 
 impl ClippyArt {
-    pub fn new(mut output_width: u16) -> Self {
-        if output_width < PREFIX.len() as u16 {
-            output_width = PREFIX.len() as u16;
-        }
+    /// Draws Clippy with a speech bubble that is `output_width` columns wide.
+    pub fn new(output_width: u16) -> Self {
+        Self::with_character(output_width, &Character::default())
+    }
 
-        let mut s = CLIPPY_ART.to_string() + PREFIX;
-        for _ in 0..output_width - PREFIX.len() as u16 {
-            s.push('‾');
+    pub fn with_character(output_width: u16, character: &Character) -> Self {
+        Self::draw(output_width, character, true)
+    }
+
+    /// Draws only the speech bubble of `character`, e.g. for the next bubble below the character.
+    pub fn without_art(output_width: u16, character: &Character) -> Self {
+        Self::draw(output_width, character, false)
+    }
+
+    fn draw(output_width: u16, character: &Character, art: bool) -> Self {
+        let tail = character.tail_column();
+        // The tail has to fit between the corners
+        // The tail column comes from character files, so it can be anything
+        let output_width = output_width.max(MIN_WIDTH).max(tail.saturating_add(3));
+        let chars = character.bubble.chars();
+
+        let mut s = String::new();
+        if art {
+            s.push_str(&character.art);
+            if !s.ends_with('\n') {
+                s.push('\n');
+            }
+        }
+        s.push(chars.top_left);
+        for column in 1..output_width - 1 {
+            // The top border is open where the tail attaches
+            if art && (tail..tail.saturating_add(2)).contains(&column) {
+                s.push(' ');
+            } else {
+                s.push(chars.top);
+            }
         }
-        s.push_str("\\\n");
+        s.push(chars.top_right);
+        s.push('\n');
         Self {
             buf: s,
             output_width,
+            bubble: character.bubble,
             line: String::new(),
-            line_char_length: 0,
+            line_width: 0,
+            style: Style::default(),
         }
     }
 
+    /// Returns the number of columns of text that fit between the borders.
+    fn text_width(&self) -> u16 {
+        self.output_width.saturating_sub(4)
+    }
+
     /// Adds text to be processed.
+    ///
+    /// Lines of code snippets are cut off at the right border instead of wrapped, so that the
+    /// markers under the code still line up with it. The labels after the markers are wrapped
+    /// inside the snippet.
     pub fn add_str(&mut self, s: &str) {
-        const PREFIX_WIDTH: u16 = PREFIX.len() as u16 - 4;
+        for line in s.split_inclusive('\n') {
+            let is_line_start = self.line_width == 0 && ansi::strip(&self.line).is_empty();
+            if is_line_start && is_snippet_line(&ansi::strip(line)) {
+                self.add_snippet_line(line);
+            } else {
+                self.add_text(line);
+            }
+        }
+    }
+
+    /// Adds text that is wrapped at the right border.
+    fn add_text(&mut self, s: &str) {
+        // Long words are broken below, because textwrap could break them inside of a grapheme
         let lines = wrap(
             s,
-            usize::min((self.output_width - PREFIX_WIDTH) as usize, 2000),
+            Options::new(usize::min(self.text_width() as usize, 2000)).break_words(false),
         );
 
         for (i, line) in lines.iter().enumerate() {
-            for char in line.chars() {
-                if char == '\n' {
-                    ClippyArt::add_string_to_buffer(
-                        &mut self.buf,
-                        &self.line,
-                        self.output_width - PREFIX_WIDTH - self.line_char_length,
-                    );
-                    self.line.clear();
-                    self.line_char_length = 0;
-                } else {
-                    self.line.push(char);
-                    self.line_char_length += 1;
-                }
+            for segment in ansi::segments(line) {
+                let text = match segment {
+                    Segment::Escape(escape) => {
+                        self.line.push_str(escape);
+                        self.style.push(escape);
+                        continue;
+                    }
+                    Segment::Text(text) => text,
+                };
+
+                // A grapheme is what the terminal displays as one character, so it is never split
+                for grapheme in text.graphemes(true) {
+                    if grapheme == "\n" || grapheme == "\r\n" {
+                        self.add_line_to_buffer();
+                        continue;
+                    }
 
-                if self.line_char_length == self.output_width - PREFIX_WIDTH {
-                    ClippyArt::add_string_to_buffer(&mut self.buf, &self.line, 0);
-                    self.line.clear();
-                    self.line_char_length = 0;
+                    let width = grapheme.width() as u16;
+                    if self.line_width + width > self.text_width() {
+                        // A wide character does not fit at the end of the line
+                        self.add_line_to_buffer();
+                    }
+                    self.line.push_str(grapheme);
+                    self.line_width += width;
+
+                    if self.line_width >= self.text_width() {
+                        self.add_line_to_buffer();
+                    }
                 }
             }
 
             if i < lines.len() - 1 {
-                ClippyArt::add_string_to_buffer(
-                    &mut self.buf,
-                    &self.line,
-                    self.output_width - PREFIX_WIDTH - self.line_char_length,
-                );
-                self.line.clear();
-                self.line_char_length = 0;
+                self.add_line_to_buffer();
+            }
+        }
+    }
+
+    /// Adds a line of a code snippet, which ends with `…` if it is too long.
+    ///
+    /// The label after the markers explains the diagnostic, so it is wrapped instead of being cut
+    /// off. Its lines are indented under the label, or under the gutter if there is no room next to
+    /// the markers, so that they stay inside the snippet.
+    fn add_snippet_line(&mut self, line: &str) {
+        let (line, has_newline) = match line.strip_suffix('\n') {
+            Some(line) => (line.strip_suffix('\r').unwrap_or(line), true),
+            None => (line, false),
+        };
+        let fits = ansi::strip(line).width() as u16 <= self.text_width();
+
+        match label_start(line).filter(|_| !fits) {
+            Some(start) => self.add_snippet_label(line, start),
+            None => self.add_snippet_code(line),
+        }
+
+        if has_newline {
+            self.add_line_to_buffer();
+        }
+    }
+
+    /// Adds a snippet line whose label starts at the byte index `start`, with the label wrapped.
+    fn add_snippet_label(&mut self, line: &str, start: usize) {
+        let (markers, label) = line.split_at(start);
+        // Continuation lines keep the vertical bars of the gutter and of multi-line spans
+        let indent: String = ansi::strip(markers)
+            .chars()
+            .map(|c| if c == '|' { c } else { ' ' })
+            .collect();
+        let indent_width = indent.width() as u16;
+
+        let (first, indent) = if self.text_width().saturating_sub(indent_width) >= MIN_LABEL_WIDTH {
+            (markers.to_string(), indent)
+        } else {
+            // The label starts on the next line, right after the gutter
+            let gutter = indent.find('|').map_or(0, |i| i + 1);
+            let gutter = format!("{} ", &indent[..gutter]);
+            if self.text_width().saturating_sub(gutter.width() as u16) < MIN_LABEL_WIDTH {
+                self.add_snippet_code(line);
+                return;
+            }
+            self.add_snippet_code(markers.trim_end());
+            self.add_line_to_buffer();
+            (gutter.clone(), gutter)
+        };
+
+        let label_width = self.text_width() - indent.width() as u16;
+        let lines = wrap(label, Options::new(label_width as usize).break_words(false));
+        for (i, label_line) in lines.iter().enumerate() {
+            if i == 0 {
+                self.add_snippet_code(&format!("{first}{label_line}"));
+            } else {
+                self.add_line_to_buffer();
+                self.add_snippet_code(&format!("{indent}{label_line}"));
             }
         }
     }
 
-    fn add_string_to_buffer(buf: &mut String, line: &str, space_count: u16) {
-        if !line.is_empty() {
-            buf.push_str("| ");
-            buf.push_str(line);
-            for _ in 0..space_count {
-                buf.push(' ');
+    /// Adds the code or markers of a snippet line without its line break, cut off with `…`.
+    fn add_snippet_code(&mut self, line: &str) {
+        let fits = ansi::strip(line).width() as u16 <= self.text_width();
+
+        let mut is_cut = false;
+        for segment in ansi::segments(line) {
+            let text = match segment {
+                // Escape sequences after the cut still change the colors
+                Segment::Escape(escape) => {
+                    self.line.push_str(escape);
+                    self.style.push(escape);
+                    continue;
+                }
+                Segment::Text(_) if is_cut => continue,
+                Segment::Text(text) => text,
+            };
+            for grapheme in text.graphemes(true) {
+                let width = grapheme.width() as u16;
+                if !fits && self.line_width + width >= self.text_width() {
+                    self.line.push('…');
+                    self.line_width += 1;
+                    is_cut = true;
+                    break;
+                }
+                self.line.push_str(grapheme);
+                self.line_width += width;
             }
-            buf.push_str(" |\n");
         }
     }
 
+    /// Moves the incomplete line into the buffer, padded to the width of the bubble.
+    ///
+    /// Colors are reset before the right border, and restored at the start of the next line.
+    fn add_line_to_buffer(&mut self) {
+        if !self.style.is_empty() {
+            self.line.push_str(ansi::RESET);
+        }
+        // A line of only escape sequences is empty
+        if !ansi::strip(&self.line).is_empty() {
+            let side = self.bubble.chars().side;
+            self.buf.push(side);
+            self.buf.push(' ');
+            self.buf.push_str(&self.line);
+            for _ in self.line_width..self.text_width() {
+                self.buf.push(' ');
+            }
+            self.buf.push(' ');
+            self.buf.push(side);
+            self.buf.push('\n');
+        }
+        self.line.clear();
+        self.line.push_str(self.style.as_str());
+        self.line_width = 0;
+    }
+
     /// Appends the last line of the speech bubble.
     ///
     /// `add_str()` or `finish()` should not be called after `finish()` was called.
     pub fn finish(&mut self) {
-        const PREFIX_WIDTH: u16 = PREFIX.len() as u16 - 4;
-        if !self.line.is_empty() {
-            ClippyArt::add_string_to_buffer(
-                &mut self.buf,
-                &self.line,
-                self.output_width - PREFIX_WIDTH - self.line.chars().count() as u16,
-            );
-            self.line.clear();
-            self.line_char_length = 0;
+        self.add_line_to_buffer();
+
+        let chars = self.bubble.chars();
+        self.buf.push(chars.bottom_left);
+        for _ in 2..self.output_width {
+            self.buf.push(chars.bottom);
         }
+        self.buf.push(chars.bottom_right);
+        self.buf.push('\n');
+    }
+}
 
-        self.buf.push('\\');
-        for _ in 0..self.output_width - PREFIX_WIDTH + 2 {
-            self.buf.push('_');
+/// Returns whether `line` is part of a code snippet, i.e. the path above the code, a line of code
+/// or the markers under it, e.g. ` --> src/main.rs:2:9`, `2 |     let a = 1;` or `  |     ^`.
+fn is_snippet_line(line: &str) -> bool {
+    let line = line.trim_start();
+    if line.starts_with("--> ") || line.starts_with("::: ") || line.trim_end() == "..." {
+        return true;
+    }
+    line.trim_start_matches(|c: char| c.is_ascii_digit())
+        .trim_start()
+        .starts_with('|')
+}
+
+/// Returns the byte index of the label after the markers of a snippet line, e.g. `help: remove
+/// this` in `  |     ^ help: remove this`. Lines of code have no label.
+fn label_start(line: &str) -> Option<usize> {
+    let mut is_gutter = true;
+    let mut has_marker = false;
+    let mut index = 0;
+    for segment in ansi::segments(line) {
+        let text = match segment {
+            Segment::Escape(escape) => {
+                index += escape.len();
+                continue;
+            }
+            Segment::Text(text) => text,
+        };
+        for (i, c) in text.char_indices() {
+            match c {
+                '|' if is_gutter => is_gutter = false,
+                c if c.is_whitespace() => {}
+                _ if is_gutter => return None,
+                '^' | '-' => has_marker = true,
+                '|' | '_' | '/' | '\\' => {}
+                _ if has_marker => return Some(index + i),
+                _ => return None,
+            }
         }
-        self.buf.push_str("/\n");
+        index += text.len();
     }
+    None
 }
 
 impl Iterator for ClippyArt {
@@ -144,6 +342,10 @@ impl Iterator for ClippyArt {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rstest::rstest;
+    use std::sync::LazyLock;
+
+    static CLIPPY_ART: LazyLock<String> = LazyLock::new(|| Character::default().art);
 
     #[test]
     fn clippy_output() {
@@ -245,4 +447,187 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn unicode_width() {
+        let clippy_str = |output_width: u16, s: &str| {
+            let mut clippy = ClippyArt::new(output_width);
+            clippy.add_str(s);
+            clippy.finish();
+            let result: String = clippy.collect();
+            result[CLIPPY_ART.len()..].to_string()
+        };
+
+        // CJK characters are two columns wide and are not split at the right border
+        assert_eq!(
+            clippy_str(0, "你好世"),
+            "/‾‾‾‾  \\\n| 你好 |\n| 世   |\n\\______/\n"
+        );
+        assert_eq!(
+            clippy_str(9, "aa你好世界"),
+            "/‾‾‾‾  ‾\\\n| aa你  |\n| 好世  |\n| 界    |\n\\_______/\n"
+        );
+
+        // An emoji ZWJ sequence is one grapheme that is two columns wide
+        assert_eq!(clippy_str(9, "👨‍👩‍👧"), "/‾‾‾‾  ‾\\\n| 👨‍👩‍👧    |\n\\_______/\n");
+        assert_eq!(
+            clippy_str(9, "🇯🇵❤️🇯🇵"),
+            "/‾‾‾‾  ‾\\\n| 🇯🇵❤️  |\n| 🇯🇵    |\n\\_______/\n"
+        );
+
+        // Combining accents take up no columns
+        assert_eq!(
+            clippy_str(0, "e\u{301}e\u{301}e\u{301}e\u{301}e\u{301}"),
+            "/‾‾‾‾  \\\n| e\u{301}e\u{301}e\u{301}e\u{301} |\n| e\u{301}    |\n\\______/\n"
+        );
+
+        // Japanese has no spaces between words, so lines break between characters, but not before
+        // punctuation
+        assert_eq!(
+            clippy_str(18, "おっと！`y` の変数が、使われる前に破棄されています。"),
+            "/‾‾‾‾  ‾‾‾‾‾‾‾‾‾‾\\\n| おっと！`y` の |\n| 変数が、使われ |\n| る前に破棄され |\n| ています。     |\n\\________________/\n"
+        );
+    }
+
+    #[test]
+    fn escape_sequences() {
+        let clippy_str = |s: &str| {
+            let mut clippy = ClippyArt::new(9);
+            clippy.add_str(s);
+            clippy.finish();
+            let result: String = clippy.collect();
+            result[CLIPPY_ART.len()..].to_string()
+        };
+
+        // Escape sequences are zero-width, and colors are reset around the borders
+        assert_eq!(
+            clippy_str("\x1b[1m\x1b[31mabcdefg\x1b[0m h"),
+            "/‾‾‾‾  ‾\\\n| \x1b[1m\x1b[31mabcde\x1b[0m |\n| \x1b[1m\x1b[31mfg\x1b[0m    |\n| h     |\n\\_______/\n"
+        );
+        assert_eq!(
+            clippy_str("a\x1b[32mbc de\x1b[0mf"),
+            "/‾‾‾‾  ‾\\\n| a\x1b[32mbc\x1b[0m   |\n| \x1b[32mde\x1b[0mf   |\n\\_______/\n"
+        );
+    }
+
+    #[test]
+    fn snippets() {
+        let clippy_str = |s: &str| {
+            let mut clippy = ClippyArt::new(16);
+            clippy.add_str(s);
+            clippy.finish();
+            let result: String = clippy.collect();
+            result[CLIPPY_ART.len()..].to_string()
+        };
+
+        // Code and markers are cut off instead of wrapped, but other lines are wrapped
+        assert_eq!(
+            clippy_str(
+                "Hmmm... unused variable.\n --> src/main.rs:2:9\n2 | let a = long_value;\n  |     ^\n"
+            ),
+            "/‾‾‾‾  ‾‾‾‾‾‾‾‾\\
+| Hmmm...      |
+| unused       |
+| variable.    |
+|  --> src/ma… |
+| 2 | let a =… |
+|   |     ^    |
+\\______________/
+"
+        );
+
+        // Labels that do not fit are wrapped inside the gutter, under the label if there is room
+        let label = clippy_str("  |     ^ help: remove this variable\n");
+        assert_eq!(
+            label,
+            "/‾‾‾‾  ‾‾‾‾‾‾‾‾\\
+|   |     ^    |
+|   | help:    |
+|   | remove   |
+|   | this     |
+|   | variable |
+\\______________/
+"
+        );
+        let mut clippy = ClippyArt::new(30);
+        clippy.add_str("  |  ___^ help: remove this variable, please\n");
+        clippy.finish();
+        let wide_label = clippy.collect::<String>()[CLIPPY_ART.len()..].to_string();
+        assert_eq!(
+            wide_label,
+            "/‾‾‾‾  ‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\\
+|   |  ___^ help: remove     |
+|   |       this variable,   |
+|   |       please           |
+\\____________________________/
+"
+        );
+        // Every line between the borders is inside the gutter
+        for bubble in [&label, &wide_label] {
+            let lines: Vec<&str> = bubble.lines().collect();
+            for line in &lines[1..lines.len() - 1] {
+                assert!(line.starts_with("|   |"), "{line}");
+            }
+        }
+        assert_eq!(
+            clippy_str("  | ^ unused\n"),
+            "/‾‾‾‾  ‾‾‾‾‾‾‾‾\\\n|   | ^ unused |\n\\______________/\n"
+        );
+
+        // Colors continue after the cut
+        assert_eq!(
+            clippy_str("\x1b[1m12 | let a = b + c;\x1b[0m\n"),
+            "/‾‾‾‾  ‾‾‾‾‾‾‾‾\\\n| \x1b[1m12 | let a …\x1b[0m |\n\\______________/\n"
+        );
+    }
+
+    #[rstest]
+    #[case("  |     ^ help: remove this", Some(10))]
+    #[case("  |     ^^^^^ \x1b[1mhelp\x1b[0m", Some(18))]
+    #[case("  |  ___^ borrow starts here", Some(10))]
+    #[case("  |     -", None)]
+    #[case("  |     |", None)]
+    #[case("2 |     let a = -b;", None)]
+    #[case(" --> src/main.rs:2:9", None)]
+    fn label_starts(#[case] line: &str, #[case] expected: Option<usize>) {
+        assert_eq!(label_start(line), expected);
+    }
+
+    #[rstest]
+    #[case(" --> src/main.rs:2:9", true)]
+    #[case("   ::: src/lib.rs:1:1", true)]
+    #[case("12 |     let a = 1;", true)]
+    #[case("  |     ^ help: remove this", true)]
+    #[case("...", true)]
+    #[case("Hmmm... unused variable.", false)]
+    #[case("  = note: `#[warn(unused)]` on by default", false)]
+    fn snippet_lines(#[case] line: &str, #[case] expected: bool) {
+        assert_eq!(is_snippet_line(line), expected);
+    }
+
+    #[test]
+    fn character() {
+        let character = Character {
+            name: String::new(),
+            art: " o\n/|\\\n /\\".to_string(),
+            tail: None,
+            bubble: BubbleStyle::Ascii,
+        };
+        let mut clippy = ClippyArt::with_character(10, &character);
+        clippy.add_str("Hi!");
+        clippy.finish();
+        let result: String = clippy.collect();
+        assert_eq!(
+            result,
+            " o\n/|\\\n /\\\n.  ------.\n| Hi!    |\n'--------'\n"
+        );
+
+        // The bubble is as wide as it needs to be for the tail, without overflowing
+        let character = Character {
+            tail: Some(u16::MAX),
+            ..character
+        };
+        let clippy = ClippyArt::with_character(10, &character);
+        assert!(clippy.collect::<String>().len() > usize::from(u16::MAX));
+    }
 }
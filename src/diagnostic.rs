@@ -0,0 +1,846 @@
+//! A typed model of rustc and clippy diagnostics.
+//!
+//! Diagnostics can be parsed from cargo's human-readable output with `parse_human()`, or from
+//! `--message-format=json` output with `parse_json()`, or grouped by crate from either with
+//! `parse_crates()`. They can be filtered and counted, and then phrased like Clippit with
+//! `replace_diagnostics()`.
+//!
+//! ```
+//! use clippit::diagnostic::{parse_human, Level};
+//!
+//! let diagnostics = parse_human(
+//!     "warning: unused variable: `a`
+//!  --> src/main.rs:2:9
+//!   |
+//! 2 |     let a = 0;
+//!   |         ^ help: if this is intentional, prefix it with an underscore: `_a`
+//!   |
+//!   = note: `#[warn(unused_variables)]` on by default
+//! ",
+//! );
+//! assert_eq!(diagnostics[0].level, Level::Warning);
+//! assert_eq!(diagnostics[0].lint_name(), Some("unused_variables"));
+//! assert_eq!(diagnostics[0].primary_span().unwrap().line_start, 2);
+//! ```
+
+use crate::ansi;
+use crate::message::{is_local_package, package_name, Message};
+use regex::Regex;
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+/// A diagnostic emitted by rustc or clippy, as found in the `message` field of cargo's
+/// `compiler-message` records.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Diagnostic {
+    pub message: String,
+    #[serde(default)]
+    pub code: Option<DiagnosticCode>,
+    pub level: Level,
+    #[serde(default)]
+    pub spans: Vec<Span>,
+    #[serde(default)]
+    pub children: Vec<Diagnostic>,
+    /// The diagnostic as rustc would have printed it without `--message-format=json`.
+    #[serde(default)]
+    pub rendered: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DiagnosticCode {
+    /// An error code such as `E0597`, or a lint name such as `clippy::double_parens`.
+    pub code: String,
+    #[serde(default)]
+    pub explanation: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Level {
+    #[serde(rename = "error: internal compiler error")]
+    InternalCompilerError,
+    Error,
+    Warning,
+    Note,
+    Help,
+    FailureNote,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Level::InternalCompilerError => "error: internal compiler error",
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Note => "note",
+            Level::Help => "help",
+            Level::FailureNote => "failure-note",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Span {
+    pub file_name: String,
+    #[serde(default)]
+    pub byte_start: u32,
+    #[serde(default)]
+    pub byte_end: u32,
+    pub line_start: usize,
+    pub line_end: usize,
+    pub column_start: usize,
+    pub column_end: usize,
+    pub is_primary: bool,
+    #[serde(default)]
+    pub text: Vec<SpanLine>,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub suggested_replacement: Option<String>,
+    #[serde(default)]
+    pub suggestion_applicability: Option<Applicability>,
+}
+
+/// A line of source code covered by a `Span`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SpanLine {
+    pub text: String,
+    pub highlight_start: usize,
+    pub highlight_end: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum Applicability {
+    MachineApplicable,
+    MaybeIncorrect,
+    HasPlaceholders,
+    Unspecified,
+}
+
+impl FromStr for Level {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "error: internal compiler error" => Level::InternalCompilerError,
+            "error" => Level::Error,
+            "warning" => Level::Warning,
+            "note" => Level::Note,
+            "help" => Level::Help,
+            "failure-note" => Level::FailureNote,
+            _ => return Err(anyhow::anyhow!("unknown level: {s}")),
+        })
+    }
+}
+
+impl Diagnostic {
+    /// Returns the error code (e.g. `E0597`), or `None` if the diagnostic is a lint or has no code.
+    pub fn error_code(&self) -> Option<&str> {
+        self.code
+            .as_ref()
+            .map(|code| code.code.as_str())
+            .filter(|code| is_error_code(code))
+    }
+
+    /// Returns the name of the lint that emitted this diagnostic, e.g. `clippy::double_parens`.
+    pub fn lint_name(&self) -> Option<&str> {
+        if self.error_code().is_some() {
+            None
+        } else {
+            self.code.as_ref().map(|code| code.code.as_str())
+        }
+    }
+
+    /// Returns `true` if this is one of the messages that rustc and cargo print at the end of a
+    /// build, such as "aborting due to previous error" or "`a` (lib) generated 1 warning".
+    pub fn is_summary(&self) -> bool {
+        static SUMMARY_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(concat!(
+                r"^(aborting due to|could not compile|build failed|\d+ warnings? emitted",
+                r"|`.*` \(.*\) generated \d+ warnings?)",
+            ))
+            .unwrap()
+        });
+
+        matches!(self.level, Level::Error | Level::Warning)
+            && self.spans.is_empty()
+            && self.code.is_none()
+            && SUMMARY_REGEX.is_match(&self.message)
+    }
+
+    /// Returns the span that the diagnostic points at.
+    pub fn primary_span(&self) -> Option<&Span> {
+        self.spans.iter().find(|span| span.is_primary)
+    }
+
+    /// Returns the spans that give more context, such as "borrow later used here".
+    pub fn secondary_spans(&self) -> impl Iterator<Item = &Span> {
+        self.spans.iter().filter(|span| !span.is_primary)
+    }
+
+    /// Returns the labels written next to the spans in the source snippet.
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.spans.iter().filter_map(|span| span.label.as_deref())
+    }
+
+    /// Returns the messages of the `note` children.
+    pub fn notes(&self) -> impl Iterator<Item = &str> {
+        self.children_messages(Level::Note)
+    }
+
+    /// Returns the messages of the `help` children.
+    pub fn help(&self) -> impl Iterator<Item = &str> {
+        self.children_messages(Level::Help)
+    }
+
+    fn children_messages(&self, level: Level) -> impl Iterator<Item = &str> {
+        self.children
+            .iter()
+            .filter(move |child| child.level == level)
+            .map(|child| child.message.as_str())
+    }
+
+    /// Returns the spans of the children that suggest a replacement for the code.
+    pub fn suggestions(&self) -> impl Iterator<Item = &Span> {
+        self.children
+            .iter()
+            .flat_map(|child| &child.spans)
+            .filter(|span| span.suggested_replacement.is_some())
+    }
+
+    /// Returns the source snippet under the first line of the rendered diagnostic, without the
+    /// notes and help that follow it.
+    pub fn snippet(&self) -> String {
+        let mut result = String::new();
+        for line in self.rendered.iter().flat_map(|s| s.lines()).skip(1) {
+            let plain = ansi::strip(line);
+            if plain.is_empty() || parse_note(&plain).is_some() || parse_header(&plain).is_some() {
+                break;
+            }
+            result.push_str(line);
+            result.push('\n');
+        }
+        result
+    }
+}
+
+/// Returns `true` for error codes such as `E0597`, as opposed to lint names.
+pub fn is_error_code(code: &str) -> bool {
+    code.len() > 1 && code.starts_with('E') && code[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+/// Parses the diagnostics in the human-readable output of cargo or rustc.
+///
+/// Lines that are not part of a diagnostic, such as "Checking ..." are skipped.
+pub fn parse_human(input: &str) -> Vec<Diagnostic> {
+    parse_human_blocks(input)
+        .into_iter()
+        .filter_map(|block| match block {
+            Block::Diagnostic(diagnostic) => Some(diagnostic),
+            Block::Line(_) => None,
+        })
+        .collect()
+}
+
+/// Parses the diagnostics in the output of `cargo --message-format=json` or
+/// `rustc --error-format=json`.
+pub fn parse_json(input: &str) -> Vec<Diagnostic> {
+    input
+        .lines()
+        .filter(|line| line.starts_with('{'))
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(Message::CompilerMessage(compiler_message)) => Some(compiler_message.message),
+            Ok(_) => None,
+            Err(_) => serde_json::from_str(line).ok(),
+        })
+        .collect()
+}
+
+/// The diagnostics about one crate.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrateDiagnostics {
+    /// Name of the crate, or empty for diagnostics that cargo did not say the crate of.
+    pub name: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Parses the diagnostics in the output of cargo, which can be human-readable or JSON, and groups
+/// them by crate. In human-readable output, the crate is the one from the last "Checking <crate>"
+/// or "Compiling <crate>" line, and in JSON output, it is the package of the record.
+///
+/// Local crates without diagnostics are included, so that each crate of the workspace that cargo
+/// checked is there.
+pub fn parse_crates(input: &str) -> Vec<CrateDiagnostics> {
+    let mut crates = Vec::new();
+    if input.lines().any(|line| line.starts_with("{\"")) {
+        for line in input.lines().filter(|line| line.starts_with('{')) {
+            match serde_json::from_str(line) {
+                Ok(Message::CompilerMessage(compiler_message)) => push_crate_diagnostic(
+                    &mut crates,
+                    package_name(&compiler_message.package_id),
+                    compiler_message.message,
+                ),
+                Ok(Message::CompilerArtifact(artifact)) => {
+                    if is_local_package(&artifact.package_id) {
+                        add_crate(&mut crates, package_name(&artifact.package_id));
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => {
+                    if let Ok(diagnostic) = serde_json::from_str(line) {
+                        push_crate_diagnostic(&mut crates, "", diagnostic);
+                    }
+                }
+            }
+        }
+    } else {
+        let mut name = String::new();
+        for block in parse_human_blocks(input) {
+            match block {
+                Block::Diagnostic(diagnostic) => {
+                    push_crate_diagnostic(&mut crates, &name, diagnostic);
+                }
+                Block::Line(line) => {
+                    if let Some((crate_name, is_local)) = parse_crate_line(&ansi::strip(&line)) {
+                        name = crate_name.to_string();
+                        if is_local {
+                            add_crate(&mut crates, &name);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    crates
+}
+
+/// Adds `diagnostic` to the crate called `name`, unless the crate already has it. Cargo repeats
+/// diagnostics for targets that share source files.
+pub fn push_crate_diagnostic(
+    crates: &mut Vec<CrateDiagnostics>,
+    name: &str,
+    diagnostic: Diagnostic,
+) {
+    let diagnostics = &mut add_crate(crates, name).diagnostics;
+    if !diagnostics.contains(&diagnostic) {
+        diagnostics.push(diagnostic);
+    }
+}
+
+/// Returns the crate called `name`, which is added if it is not in `crates` yet.
+pub fn add_crate<'a>(
+    crates: &'a mut Vec<CrateDiagnostics>,
+    name: &str,
+) -> &'a mut CrateDiagnostics {
+    let i = match crates.iter().position(|c| c.name == name) {
+        Some(i) => i,
+        None => {
+            crates.push(CrateDiagnostics {
+                name: name.to_string(),
+                diagnostics: Vec::new(),
+            });
+            crates.len() - 1
+        }
+    };
+    &mut crates[i]
+}
+
+/// Returns the crate name of a line such as "    Checking playground v0.0.1 (/playground)", and
+/// whether the crate is in a local path.
+fn parse_crate_line(line: &str) -> Option<(&str, bool)> {
+    let rest = line
+        .trim_start()
+        .strip_prefix("Checking ")
+        .or_else(|| line.trim_start().strip_prefix("Compiling "))?;
+    let (name, version) = rest.split_once(' ')?;
+    if !version.starts_with('v') {
+        return None;
+    }
+    // Dependencies from git also have their source in parentheses, but as a URL
+    let is_local = version
+        .split_once(" (")
+        .is_some_and(|(_, source)| !source.contains("://"));
+    Some((name, is_local))
+}
+
+/// A diagnostic or a line outside of any diagnostic in human-readable output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Block {
+    Diagnostic(Diagnostic),
+    Line(String),
+}
+
+// The lines of diagnostics are parsed without regexes because this is much faster, and large
+// projects can have megabytes of output.
+
+/// Splits "warning: ..." or "error[E0597]: ..." into the level, the code and the message.
+fn parse_header(line: &str) -> Option<(Level, Option<&str>, &str)> {
+    if let Some(message) = line.strip_prefix("error: internal compiler error: ") {
+        return Some((Level::InternalCompilerError, None, message));
+    }
+
+    if !line.starts_with(|c: char| c.is_ascii_lowercase()) {
+        return None;
+    }
+    let (prefix, message) = line.split_once(": ")?;
+    let (level, code) = match prefix.split_once('[') {
+        Some((level, code)) => {
+            let code = code.strip_suffix(']')?;
+            if code.is_empty() || code.contains(char::is_whitespace) {
+                return None;
+            }
+            (level, Some(code))
+        }
+        None => (prefix, None),
+    };
+    // Parsing fails for most lines, and creating the error is slow
+    if !level.bytes().all(|b| b.is_ascii_lowercase() || b == b'-') {
+        return None;
+    }
+    Some((level.parse().ok()?, code, message))
+}
+
+/// Splits "  = note: ..." into the level and the message.
+fn parse_note(line: &str) -> Option<(Level, &str)> {
+    let (level, message) = line.trim_start().strip_prefix("= ")?.split_once(": ")?;
+    match level.parse() {
+        Ok(level @ (Level::Note | Level::Help | Level::Warning | Level::Error)) => {
+            Some((level, message))
+        }
+        _ => None,
+    }
+}
+
+/// Splits " --> src/main.rs:5:13" into whether the span is primary, the file name, the line and
+/// the column. Secondary locations start with ":::".
+fn parse_location(line: &str) -> Option<(bool, &str, usize, usize)> {
+    let trimmed = line.trim_start();
+    let (is_primary, location) = match trimmed.strip_prefix("--> ") {
+        Some(location) => (true, location),
+        None => (false, trimmed.strip_prefix("::: ")?),
+    };
+    let mut parts = location.rsplitn(3, ':');
+    let column = parts.next()?.parse().ok()?;
+    let line = parts.next()?.parse().ok()?;
+    let file_name = parts.next().filter(|file_name| !file_name.is_empty())?;
+    Some((is_primary, file_name, line, column))
+}
+
+/// Returns the line number of a line of a source snippet such as "5 |         b = &a;".
+fn parse_source_line(line: &str) -> Option<usize> {
+    let trimmed = line.trim_start();
+    let digits = trimmed.find(|c: char| !c.is_ascii_digit())?;
+    if digits == 0 || !trimmed[digits..].starts_with(" |") {
+        return None;
+    }
+    trimmed[..digits].parse().ok()
+}
+
+/// Splits "  |     ^^ label" into the columns of the `^^^` or `---` marker and the label.
+fn parse_marker(line: &str) -> Option<(Range<usize>, bool, Option<&str>)> {
+    let rest = line.trim_start().strip_prefix("| ")?;
+    let start = rest.find(|c| !matches!(c, ' ' | '|' | '_'))?;
+    let marker_char = rest[start..].chars().next()?;
+    if marker_char != '^' && marker_char != '-' {
+        return None;
+    }
+    let end = rest[start..]
+        .find(|c| c != marker_char)
+        .map_or(rest.len(), |len| start + len);
+    let label = match &rest[end..] {
+        "" => None,
+        after => Some(after.strip_prefix(' ').filter(|label| !label.is_empty())?),
+    };
+    // Columns start at 1 after the gutter
+    Some((start + 1..end + 1, marker_char == '^', label))
+}
+
+/// Returns `true` for lines of source snippets, which start with "|" or a line number and "|".
+fn is_gutter(line: &str) -> bool {
+    line.trim_start()
+        .trim_start_matches(|c: char| c.is_ascii_digit())
+        .trim_start()
+        .starts_with('|')
+}
+
+pub(crate) fn parse_human_blocks(input: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut parser: Option<HumanParser> = None;
+
+    for line in input.lines() {
+        // Colored lines are parsed without their colors, but the rendered diagnostic keeps them
+        let plain = ansi::strip(line);
+        if let Some(p) = &mut parser {
+            if p.push_line(&plain, line) {
+                continue;
+            }
+            blocks.push(Block::Diagnostic(parser.take().unwrap().finish()));
+        }
+
+        match parse_header(&plain) {
+            Some((level, code, message)) => {
+                parser = Some(HumanParser::new(line, level, code, message));
+            }
+            None => blocks.push(Block::Line(line.to_string())),
+        }
+    }
+
+    if let Some(parser) = parser {
+        blocks.push(Block::Diagnostic(parser.finish()));
+    }
+    blocks
+}
+
+/// Builds a `Diagnostic` from the lines of its rendered form.
+struct HumanParser {
+    diagnostic: Diagnostic,
+    rendered: String,
+    /// Index of the child that has its own source snippet, which spans are added to
+    section: Option<usize>,
+    /// Index of the "= note:" child that continuation lines are added to, and the indentation of
+    /// the continuation lines
+    note: Option<(usize, usize)>,
+    file_name: String,
+    source_line: usize,
+}
+
+impl HumanParser {
+    fn new(line: &str, level: Level, code: Option<&str>, message: &str) -> Self {
+        Self {
+            diagnostic: Diagnostic {
+                message: message.to_string(),
+                code: code.map(|code| DiagnosticCode {
+                    code: code.to_string(),
+                    explanation: None,
+                }),
+                level,
+                spans: Vec::new(),
+                children: Vec::new(),
+                rendered: None,
+            },
+            rendered: line.to_string() + "\n",
+            section: None,
+            note: None,
+            file_name: String::new(),
+            source_line: 0,
+        }
+    }
+
+    /// Adds a line to the diagnostic. `line` is `colored` without escape sequences. Returns `false`
+    /// if the line is not part of the diagnostic.
+    fn push_line(&mut self, line: &str, colored: &str) -> bool {
+        if line.is_empty() {
+            return false;
+        }
+
+        if let Some((level, message)) = parse_note(line) {
+            self.diagnostic.children.push(child(level, message));
+            self.note = Some((
+                self.diagnostic.children.len() - 1,
+                line.len() - message.len(),
+            ));
+        } else if let Some((level @ (Level::Note | Level::Help), _, message)) = parse_header(line) {
+            self.diagnostic.children.push(child(level, message));
+            self.section = Some(self.diagnostic.children.len() - 1);
+            self.note = None;
+        } else if let Some((is_primary, file_name, source_line, column)) = parse_location(line) {
+            self.file_name = file_name.to_string();
+            self.source_line = source_line;
+            let span = span(&self.file_name, source_line, column, is_primary);
+            self.current().spans.push(span);
+        } else if let Some(source_line) = parse_source_line(line) {
+            self.source_line = source_line;
+        } else if let Some((columns, is_primary, label)) = parse_marker(line) {
+            self.add_label(columns, is_primary, label);
+        } else if let Some((index, _)) = self
+            .note
+            .filter(|(_, indent)| line.len() - line.trim_start_matches(' ').len() >= *indent)
+        {
+            let note = &mut self.diagnostic.children[index];
+            note.message.push('\n');
+            note.message.push_str(line.trim_start());
+        } else if !is_gutter(line) && !line.starts_with("...") {
+            // Cargo's status messages such as "    Finished" are not indented as much as notes
+            return false;
+        }
+
+        self.rendered.push_str(colored);
+        self.rendered.push('\n');
+        true
+    }
+
+    /// Returns the diagnostic or the child that the current source snippet belongs to.
+    fn current(&mut self) -> &mut Diagnostic {
+        match self.section {
+            Some(index) => &mut self.diagnostic.children[index],
+            None => &mut self.diagnostic,
+        }
+    }
+
+    /// Adds the label of a `^^^` or `---` marker to the span at `columns`.
+    fn add_label(&mut self, columns: Range<usize>, is_primary: bool, label: Option<&str>) {
+        let source_line = self.source_line;
+        let mut new_span = span(&self.file_name, source_line, columns.start, is_primary);
+        new_span.column_end = columns.end;
+
+        // "help: ..." labels are suggestions that rustc shows inline
+        if let Some(message) = label.and_then(|label| label.strip_prefix("help: ")) {
+            let mut help = child(Level::Help, message);
+            help.spans.push(new_span);
+            self.current().children.push(help);
+            return;
+        }
+
+        let current = self.current();
+        if let Some(span) = current.spans.iter_mut().find(|span| {
+            span.is_primary == is_primary
+                && span.line_start == source_line
+                && span.column_start == columns.start
+                && span.label.is_none()
+        }) {
+            span.column_end = columns.end;
+            span.label = label.map(str::to_string);
+        } else if label.is_some() || is_primary {
+            new_span.label = label.map(str::to_string);
+            current.spans.push(new_span);
+        }
+    }
+
+    fn finish(mut self) -> Diagnostic {
+        static LINT_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"`#\[(?:warn|deny|forbid|expect)\(([\w:]+)\)\]`").unwrap()
+        });
+
+        // Lints are only named in notes such as "`#[warn(unused_variables)]` on by default"
+        if self.diagnostic.code.is_none() {
+            let lint_name = self
+                .diagnostic
+                .notes()
+                .filter(|note| note.contains("`#["))
+                .find_map(|note| LINT_REGEX.captures(note))
+                .map(|caps| caps[1].to_string());
+            self.diagnostic.code = lint_name.map(|code| DiagnosticCode {
+                code,
+                explanation: None,
+            });
+        }
+
+        self.diagnostic.rendered = Some(self.rendered);
+        self.diagnostic
+    }
+}
+
+fn child(level: Level, message: &str) -> Diagnostic {
+    Diagnostic {
+        message: message.to_string(),
+        code: None,
+        level,
+        spans: Vec::new(),
+        children: Vec::new(),
+        rendered: None,
+    }
+}
+
+fn span(file_name: &str, line: usize, column: usize, is_primary: bool) -> Span {
+    Span {
+        file_name: file_name.to_string(),
+        byte_start: 0,
+        byte_end: 0,
+        line_start: line,
+        line_end: line,
+        column_start: column,
+        column_end: column,
+        is_primary,
+        text: Vec::new(),
+        label: None,
+        suggested_replacement: None,
+        suggestion_applicability: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BORROW_ERROR: &str = r#"error[E0597]: `a` does not live long enough
+ --> src/main.rs:5:13
+  |
+4 |         let a = 0;
+  |             - binding `a` declared here
+5 |         b = &a;
+  |             ^^ borrowed value does not live long enough
+6 |     }
+  |     - `a` dropped here while still borrowed
+7 |     println!("{}", b);
+  |                    - borrow later used here
+"#;
+
+    #[test]
+    fn test_parse_human() {
+        let diagnostics = parse_human(
+            &("    Checking playground v0.0.1 (/playground)\n".to_string()
+                + BORROW_ERROR
+                + "\nFor more information about this error, try `rustc --explain E0597`.\n"),
+        );
+        assert_eq!(diagnostics.len(), 1);
+
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic.level, Level::Error);
+        assert_eq!(diagnostic.error_code(), Some("E0597"));
+        assert_eq!(diagnostic.lint_name(), None);
+        assert_eq!(diagnostic.message, "`a` does not live long enough");
+        assert_eq!(diagnostic.rendered.as_deref(), Some(BORROW_ERROR));
+
+        let primary_span = diagnostic.primary_span().unwrap();
+        assert_eq!(primary_span.file_name, "src/main.rs");
+        assert_eq!(primary_span.line_start, 5);
+        assert_eq!(primary_span.column_start, 13);
+        assert_eq!(primary_span.column_end, 15);
+        assert_eq!(
+            primary_span.label.as_deref(),
+            Some("borrowed value does not live long enough")
+        );
+
+        let secondary_spans: Vec<(usize, usize, &str)> = diagnostic
+            .secondary_spans()
+            .map(|span| {
+                (
+                    span.line_start,
+                    span.column_start,
+                    span.label.as_deref().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            secondary_spans,
+            vec![
+                (4, 13, "binding `a` declared here"),
+                (6, 5, "`a` dropped here while still borrowed"),
+                (7, 20, "borrow later used here"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_human_children() {
+        let input = r#"error: calls to `std::mem::drop` with a value that implements `Copy` does nothing
+ --> src/main.rs:4:5
+  |
+4 |     std::mem::drop(x);
+  |     ^^^^^^^^^^^^^^^-^
+  |                    |
+  |                    argument has type `i32`
+  |
+  = note: use `let _ = ...` to ignore the expression or result
+note: the lint level is defined here
+ --> src/main.rs:1:8
+  |
+1 | #[deny(clippy::drop_copy)]
+  |        ^^^^^^^^^^^^^^^^^
+warning: `playground` (bin "playground") generated 1 warning
+    Finished dev [unoptimized + debuginfo] target(s) in 0.41s
+"#;
+        let diagnostics = parse_human(input);
+        assert_eq!(diagnostics.len(), 2);
+        assert!(!diagnostics[0].is_summary());
+        assert!(diagnostics[1].is_summary());
+
+        let diagnostic = &diagnostics[0];
+        assert_eq!(
+            diagnostic.notes().collect::<Vec<&str>>(),
+            vec![
+                "use `let _ = ...` to ignore the expression or result",
+                "the lint level is defined here",
+            ]
+        );
+        assert_eq!(
+            diagnostic.snippet(),
+            r#" --> src/main.rs:4:5
+  |
+4 |     std::mem::drop(x);
+  |     ^^^^^^^^^^^^^^^-^
+  |                    |
+  |                    argument has type `i32`
+  |
+"#
+        );
+        let section = &diagnostic.children[1];
+        assert_eq!(section.primary_span().unwrap().line_start, 1);
+        assert_eq!(section.primary_span().unwrap().column_end, 25);
+    }
+
+    #[test]
+    fn test_parse_human_inline_help() {
+        let input = r#"warning: unused variable: `pi`
+ --> src/main.rs:6:9
+  |
+6 |     let pi = 3.14;
+  |         ^^ help: if this is intentional, prefix it with an underscore: `_pi`
+  |
+  = note: `#[warn(unused_variables)]` on by default
+"#;
+        let diagnostics = parse_human(input);
+        assert_eq!(diagnostics[0].lint_name(), Some("unused_variables"));
+        assert_eq!(
+            diagnostics[0].help().collect::<Vec<&str>>(),
+            vec!["if this is intentional, prefix it with an underscore: `_pi`"]
+        );
+        assert_eq!(diagnostics[0].children[0].spans[0].column_start, 9);
+    }
+
+    #[test]
+    fn test_parse_crates() {
+        let human = "    Checking serde v1.0.188
+    Checking playground v0.0.1 (/playground)
+warning: unused variable: `a`
+ --> src/main.rs:2:9
+
+    Checking other v0.1.0 (/playground/other)
+   Compiling git-dependency v0.2.0 (https://github.com/a/b#0123abcd)
+error: expected item, found `.`
+ --> src/lib.rs:1:1
+";
+        let crates = parse_crates(human);
+        let names: Vec<&str> = crates.iter().map(|c| c.name.as_str()).collect();
+        // Dependencies are only there if they have diagnostics
+        assert_eq!(names, ["playground", "other", "git-dependency"]);
+        assert_eq!(crates[0].diagnostics[0].message, "unused variable: `a`");
+        assert!(crates[1].diagnostics.is_empty());
+        assert_eq!(crates[2].diagnostics[0].level, Level::Error);
+
+        let json = r#"{"reason":"compiler-artifact","package_id":"registry+https://github.com/rust-lang/crates.io-index#serde@1.0.188","target":{"kind":["lib"],"name":"serde"},"fresh":true}
+{"reason":"compiler-message","package_id":"path+file:///playground#0.0.1","target":{"kind":["bin"],"name":"playground"},"message":{"rendered":null,"children":[],"level":"warning","message":"unused variable: `pi`","spans":[],"code":{"code":"unused_variables","explanation":null}}}
+{"reason":"compiler-message","package_id":"path+file:///playground#0.0.1","target":{"kind":["lib"],"name":"playground"},"message":{"rendered":null,"children":[],"level":"warning","message":"unused variable: `pi`","spans":[],"code":{"code":"unused_variables","explanation":null}}}
+{"reason":"compiler-artifact","package_id":"path+file:///playground/other#0.1.0","target":{"kind":["lib"],"name":"other"},"fresh":false}
+"#;
+        let crates = parse_crates(json);
+        let names: Vec<&str> = crates.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["playground", "other"]);
+        // Cargo repeats the diagnostics of targets that share source files
+        assert_eq!(crates[0].diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let input = r#"{"reason":"compiler-message","package_id":"path+file:///playground#0.0.1","target":{"kind":["bin"],"name":"playground"},"message":{"rendered":"warning: unused variable: `pi`\n","children":[{"children":[],"code":null,"level":"help","message":"if this is intentional, prefix it with an underscore","rendered":null,"spans":[{"byte_end":68,"byte_start":66,"column_end":11,"column_start":9,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":5,"line_start":5,"suggested_replacement":"_pi","suggestion_applicability":"MachineApplicable","text":[{"highlight_end":11,"highlight_start":9,"text":"    let pi = 3.14;;"}]}]}],"level":"warning","message":"unused variable: `pi`","spans":[{"byte_end":68,"byte_start":66,"column_end":11,"column_start":9,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":5,"line_start":5,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}],"code":{"code":"unused_variables","explanation":null}}}
+{"reason":"build-finished","success":true}
+{"$message_type":"diagnostic","message":"aborting due to 1 previous error","code":null,"level":"error","spans":[],"children":[],"rendered":"error: aborting due to 1 previous error\n\n"}
+"#;
+        let diagnostics = parse_json(input);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].lint_name(), Some("unused_variables"));
+
+        let suggestions: Vec<&Span> = diagnostics[0].suggestions().collect();
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].suggested_replacement.as_deref(), Some("_pi"));
+        assert_eq!(
+            suggestions[0].suggestion_applicability,
+            Some(Applicability::MachineApplicable)
+        );
+
+        assert!(diagnostics[1].is_summary());
+    }
+}
@@ -0,0 +1,168 @@
+//! Lints and error codes that the user does not want to see tips about again.
+//!
+//! The list is kept per workspace in `.clippit/dismissed` under the workspace root, one lint name
+//! or error code per line, so that it is the same in each directory of the workspace.
+//! `Dismissed::export_lints()` turns it into a `[lints]` table for `Cargo.toml`, so that the lints
+//! are allowed for everyone who works on the project.
+
+use crate::diagnostic::is_error_code;
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Where the list is kept, relative to the root from `workspace::workspace_root()`.
+pub const DISMISSED_PATH: &str = ".clippit/dismissed";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dismissed {
+    path: PathBuf,
+    codes: BTreeSet<String>,
+}
+
+impl Dismissed {
+    /// Reads the list at `path`, which is empty if the file does not exist.
+    pub fn load(path: &Path) -> Result<Self> {
+        let codes = if path.exists() {
+            std::fs::read_to_string(path)
+                .with_context(|| format!("could not read {}", path.display()))?
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string)
+                .collect()
+        } else {
+            BTreeSet::new()
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            codes,
+        })
+    }
+
+    /// Returns `true` if `code`, a lint name such as `clippy::double_parens` or an error code such
+    /// as `E0597`, was dismissed.
+    pub fn contains(&self, code: &str) -> bool {
+        self.codes.contains(code)
+    }
+
+    pub fn insert(&mut self, code: &str) {
+        self.codes.insert(code.to_string());
+    }
+
+    /// Returns the dismissed lint names and error codes in alphabetical order.
+    pub fn codes(&self) -> impl Iterator<Item = &str> {
+        self.codes.iter().map(String::as_str)
+    }
+
+    /// Forgets all dismissed lints and error codes, and removes the file.
+    pub fn reset(&mut self) -> Result<()> {
+        self.codes.clear();
+        if self.path.exists() {
+            std::fs::remove_file(&self.path)
+                .with_context(|| format!("could not remove {}", self.path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the dismissed lints as `[lints]` tables for `Cargo.toml` that allow them. Error
+    /// codes cannot be allowed, so they are only listed in a comment.
+    pub fn export_lints(&self) -> String {
+        let mut tables: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        let mut error_codes = Vec::new();
+        for code in &self.codes {
+            match code.split_once("::") {
+                Some((tool, lint)) => tables.entry(tool).or_default().push(lint),
+                None if is_error_code(code) => error_codes.push(code.as_str()),
+                None => tables.entry("rust").or_default().push(code),
+            }
+        }
+
+        let mut result = String::new();
+        if !error_codes.is_empty() {
+            result.push_str(&format!(
+                "# Errors cannot be allowed: {}\n",
+                error_codes.join(", ")
+            ));
+        }
+        for (tool, lints) in tables {
+            if !result.is_empty() {
+                result.push('\n');
+            }
+            result.push_str(&format!("[lints.{tool}]\n"));
+            for lint in lints {
+                result.push_str(&format!("{lint} = \"allow\"\n"));
+            }
+        }
+        result
+    }
+
+    /// Writes the list back to its file.
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("could not create {}", dir.display()))?;
+        }
+        let mut s = String::new();
+        for code in &self.codes {
+            s.push_str(code);
+            s.push('\n');
+        }
+        std::fs::write(&self.path, s)
+            .with_context(|| format!("could not write {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dismissed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DISMISSED_PATH);
+
+        let mut dismissed = Dismissed::load(&path).unwrap();
+        assert!(!dismissed.contains("clippy::double_parens"));
+        dismissed.insert("clippy::double_parens");
+        dismissed.insert("E0597");
+        dismissed.save().unwrap();
+
+        let mut dismissed = Dismissed::load(&path).unwrap();
+        assert!(dismissed.contains("clippy::double_parens"));
+        assert_eq!(
+            dismissed.codes().collect::<Vec<_>>(),
+            ["E0597", "clippy::double_parens"]
+        );
+
+        dismissed.reset().unwrap();
+        assert!(!path.exists());
+        assert_eq!(dismissed.codes().count(), 0);
+    }
+
+    #[test]
+    fn test_export_lints() {
+        let mut dismissed = Dismissed::default();
+        for code in [
+            "clippy::double_parens",
+            "unused_variables",
+            "E0597",
+            "clippy::unwrap_used",
+            "dead_code",
+        ] {
+            dismissed.insert(code);
+        }
+        assert_eq!(
+            dismissed.export_lints(),
+            r#"# Errors cannot be allowed: E0597
+
+[lints.clippy]
+double_parens = "allow"
+unwrap_used = "allow"
+
+[lints.rust]
+dead_code = "allow"
+unused_variables = "allow"
+"#
+        );
+    }
+}
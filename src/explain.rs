@@ -0,0 +1,233 @@
+//! Explanations of lints and error codes in Clippit's voice.
+//!
+//! The documentation of clippy's lints is bundled in `src/lints.toml`, so that it is available
+//! without an internet connection. It is generated from the installed clippy by
+//! `cargo run --example generate_lints`. Error codes are explained by the local `rustc --explain`.
+
+use crate::locale::Catalog;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::process::Command;
+use std::sync::{LazyLock, Mutex};
+
+const LINT_DOCS: &str = include_str!("lints.toml");
+
+/// The documentation of a clippy lint.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct LintDoc {
+    /// "What it does"
+    pub what: String,
+    /// "Why is this bad?"
+    pub why: String,
+    #[serde(default)]
+    pub example: String,
+}
+
+/// Returns the documentation of the clippy lint `name`, e.g. `clippy::double_parens`. The
+/// `clippy::` prefix can be left out, and the name can be written with dashes.
+pub fn lint_doc(name: &str) -> Option<&'static LintDoc> {
+    static DOCS: LazyLock<BTreeMap<String, LintDoc>> =
+        LazyLock::new(|| toml::from_str(LINT_DOCS).unwrap());
+
+    let name = name.strip_prefix("clippy::").unwrap_or(name);
+    DOCS.get(&name.replace('-', "_"))
+}
+
+/// Phrases what the clippy lint `name` does and why, followed by its example. The documentation
+/// itself is always in English.
+pub fn explain_lint(name: &str, catalog: &Catalog) -> Option<String> {
+    let doc = lint_doc(name)?;
+    let name = format!(
+        "clippy::{}",
+        name.strip_prefix("clippy::")
+            .unwrap_or(name)
+            .replace('-', "_")
+    );
+
+    // "Checks for ..." reads as "`clippy::double_parens` checks for ..." in English
+    let what = doc.what.trim_end();
+    let first_word = what.split(' ').next().unwrap_or_default();
+    let mut result = if catalog.language() == "en"
+        && first_word.ends_with('s')
+        && !matches!(first_word, "This" | "Its" | "Is")
+    {
+        let what = format!("{}{}", first_word.to_lowercase(), &what[first_word.len()..]);
+        catalog.text("lint-does", &[("lint", name.as_str()), ("what", &what)])
+    } else {
+        catalog.text("lint-about", &[("lint", name.as_str()), ("what", what)])
+    };
+    result.push('\n');
+    if !doc.why.is_empty() {
+        result.push_str(&catalog.text("lint-why", &[("why", &doc.why)]));
+    }
+    if !doc.example.is_empty() {
+        result.push_str(&catalog.text("lint-example", &[]));
+        result.push('\n');
+        result.push_str(&doc.example);
+    }
+    Some(result)
+}
+
+/// Returns the clippy lint that a line of a diagnostic mentions, e.g. in a note about
+/// `#[warn(clippy::double_parens)]` or in the link to the lint's documentation.
+pub fn clippy_lint_in(line: &str) -> Option<&str> {
+    static LINT_REGEX: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"rust-clippy/\S*#([a-z0-9_]+)|clippy::([a-z0-9_]+)").unwrap());
+
+    let captures = LINT_REGEX.captures(line)?;
+    captures
+        .get(1)
+        .or_else(|| captures.get(2))
+        .map(|m| m.as_str())
+        .filter(|name| lint_doc(name).is_some())
+}
+
+/// Explains error codes such as `E0597` with `rustc --explain`. Each error code is only looked up
+/// once.
+pub struct ErrorExplainer {
+    lookup: fn(&str) -> Option<String>,
+    /// The first paragraph of the documentation of each error code that was looked up
+    cache: Mutex<HashMap<String, Option<String>>>,
+}
+
+impl ErrorExplainer {
+    /// Returns an `ErrorExplainer` that gets the documentation of an error code from `lookup`
+    /// instead of from rustc.
+    pub fn with_lookup(lookup: fn(&str) -> Option<String>) -> Self {
+        Self {
+            lookup,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Phrases the first paragraph of the documentation of `code`, or returns `None` if rustc
+    /// does not know the code. The documentation itself is always in English.
+    pub fn explain(&self, code: &str, catalog: &Catalog) -> Option<String> {
+        let mut cache = self.cache.lock().unwrap();
+        let summary = cache
+            .entry(code.to_string())
+            .or_insert_with(|| first_paragraph(&(self.lookup)(code)?))
+            .as_deref()?;
+        let mut result = catalog.text("error-means", &[("code", code), ("summary", summary)]);
+        result.push('\n');
+        Some(result)
+    }
+}
+
+impl Default for ErrorExplainer {
+    fn default() -> Self {
+        Self::with_lookup(rustc_explain)
+    }
+}
+
+/// Returns the output of `rustc --explain code`, which is markdown.
+fn rustc_explain(code: &str) -> Option<String> {
+    // Cargo tells build scripts and subcommands which rustc it uses
+    let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = Command::new(rustc)
+        .args(["--explain", code])
+        .output()
+        .ok()?;
+    if output.status.success() {
+        String::from_utf8(output.stdout).ok()
+    } else {
+        None
+    }
+}
+
+/// Returns the first paragraph of markdown in one line, skipping headings such as "Note: this
+/// error code is no longer emitted by the compiler".
+fn first_paragraph(markdown: &str) -> Option<String> {
+    let paragraph: Vec<&str> = markdown
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.is_empty() || line.starts_with('#'))
+        .take_while(|line| !line.is_empty())
+        .collect();
+    if paragraph.is_empty() || paragraph[0].starts_with("```") {
+        None
+    } else {
+        Some(paragraph.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_explain_lint() {
+        assert_eq!(
+            explain_lint("double-parens", &Catalog::default()).unwrap(),
+            "Did you know? `clippy::double_parens` checks for unnecessary double parentheses.
+Why? This makes code harder to read and might indicate a mistake.
+For example:
+    fn simple_double_parens() -> i32 {
+        ((0))
+    }
+
+    foo((0));
+
+Use instead:
+    fn simple_no_parens() -> i32 {
+        (0)
+    }
+
+    foo(0);
+"
+        );
+        assert_eq!(
+            explain_lint("clippy::no_such_lint", &Catalog::default()),
+            None
+        );
+        assert!(explain_lint("double-parens", &Catalog::new("de"))
+            .unwrap()
+            .starts_with(
+                "Wusstest du schon? Darum geht es bei `clippy::double_parens`: Checks for"
+            ));
+    }
+
+    #[test]
+    fn test_error_explainer() {
+        static LOOKUPS: AtomicUsize = AtomicUsize::new(0);
+        fn lookup(code: &str) -> Option<String> {
+            LOOKUPS.fetch_add(1, Ordering::SeqCst);
+            (code == "E0597").then(|| {
+                "#### Note: this is a test\n\nThis error occurs because a value was dropped\nwhile it was still borrowed.\n\nErroneous code example:\n".to_string()
+            })
+        }
+
+        let explainer = ErrorExplainer::with_lookup(lookup);
+        let catalog = Catalog::default();
+        let expected = "Here's what E0597 means: This error occurs because a value was dropped while it was still borrowed.\n";
+        assert_eq!(
+            explainer.explain("E0597", &catalog).as_deref(),
+            Some(expected)
+        );
+        assert_eq!(
+            explainer.explain("E0597", &catalog).as_deref(),
+            Some(expected)
+        );
+        assert_eq!(explainer.explain("E9999", &catalog), None);
+        assert_eq!(explainer.explain("E9999", &catalog), None);
+        assert_eq!(LOOKUPS.load(Ordering::SeqCst), 2);
+    }
+
+    #[rstest]
+    #[case(
+        "  = help: for further information visit https://rust-lang.github.io/rust-clippy/master/index.html#double_parens",
+        Some("double_parens")
+    )]
+    #[case(
+        "  = note: `#[warn(clippy::unwrap_used)]` on by default",
+        Some("unwrap_used")
+    )]
+    #[case("  = note: `-W clippy::pedantic` implied by `-W clippy::all`", None)]
+    #[case("  = note: `#[warn(unused_variables)]` on by default", None)]
+    fn test_clippy_lint_in(#[case] line: &str, #[case] expected: Option<&str>) {
+        assert_eq!(clippy_lint_in(line), expected);
+    }
+}
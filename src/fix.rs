@@ -0,0 +1,225 @@
+//! Applies the suggestions that rustc and clippy are sure about to the source files.
+//!
+//! Only suggestions with `Applicability::MachineApplicable` are applied, the same ones that
+//! `cargo clippy --fix` applies.
+
+use crate::diagnostic::{Applicability, Diagnostic, Span};
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::ops::Range;
+use std::path::Path;
+
+/// A replacement of a byte range in a source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub file_name: String,
+    pub byte_range: Range<usize>,
+    pub replacement: String,
+}
+
+impl Edit {
+    /// Returns `true` if both edits change the same part of the same file, so that only one of
+    /// them can be applied.
+    pub fn overlaps(&self, other: &Edit) -> bool {
+        self.file_name == other.file_name
+            && self.byte_range.start < other.byte_range.end.max(other.byte_range.start + 1)
+            && other.byte_range.start < self.byte_range.end.max(self.byte_range.start + 1)
+    }
+}
+
+/// Returns the edits of the first machine-applicable suggestion of `diagnostic`. Each `help` child
+/// is a suggestion of its own, and rustc can offer several alternatives that change the same code.
+pub fn edits(diagnostic: &Diagnostic) -> Vec<Edit> {
+    let is_applicable =
+        |span: &Span| span.suggestion_applicability == Some(Applicability::MachineApplicable);
+    let Some(child) = diagnostic
+        .children
+        .iter()
+        .find(|child| child.spans.iter().any(is_applicable))
+    else {
+        return Vec::new();
+    };
+    child
+        .spans
+        .iter()
+        .filter(|span| span.suggested_replacement.is_some() && is_applicable(span))
+        .map(|span| Edit {
+            file_name: span.file_name.clone(),
+            byte_range: span.byte_start as usize..span.byte_end as usize,
+            replacement: span.suggested_replacement.clone().unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Returns `source` with `edits` applied. The edits must be for the same file and must not
+/// overlap.
+pub fn apply_edits(source: &str, edits: &[&Edit]) -> Result<String> {
+    let mut edits = edits.to_vec();
+    edits.sort_by_key(|edit| edit.byte_range.start);
+
+    let mut result = String::new();
+    let mut end = 0;
+    for edit in edits {
+        let range = edit.byte_range.clone();
+        if range.start < end {
+            bail!("overlapping suggestions at byte {}", range.start);
+        }
+        if source.get(range.clone()).is_none() {
+            bail!("suggestion at bytes {range:?} is outside of the source");
+        }
+        result.push_str(&source[end..range.start]);
+        result.push_str(&edit.replacement);
+        end = range.end;
+    }
+    result.push_str(&source[end..]);
+    Ok(result)
+}
+
+/// Returns the lines that differ between `source` and `fixed`, prefixed with their line number
+/// and `-` or `+`.
+pub fn diff(source: &str, fixed: &str) -> String {
+    let old: Vec<&str> = source.lines().collect();
+    let new: Vec<&str> = fixed.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut result = String::new();
+    for (i, line) in old[prefix..old.len() - suffix].iter().enumerate() {
+        writeln!(result, "{:>4} - {line}", prefix + i + 1).unwrap();
+    }
+    for (i, line) in new[prefix..new.len() - suffix].iter().enumerate() {
+        writeln!(result, "{:>4} + {line}", prefix + i + 1).unwrap();
+    }
+    result
+}
+
+/// Returns the diff of each file that `edits` change, under the file's name. The file names are
+/// relative to `root`, the workspace root.
+pub fn preview(edits: &[Edit], root: &Path) -> Result<String> {
+    let mut result = String::new();
+    for (file_name, edits) in by_file(edits) {
+        let source = read(&root.join(&file_name))?;
+        let fixed = apply_edits(&source, &edits)?;
+        writeln!(result, "{file_name}:").unwrap();
+        result.push_str(&diff(&source, &fixed));
+    }
+    Ok(result)
+}
+
+/// Applies `edits` to the files on disk, and returns the number of files that were changed. The
+/// file names are relative to `root`, the workspace root.
+pub fn apply(edits: &[Edit], root: &Path) -> Result<usize> {
+    let files = by_file(edits);
+    for (file_name, edits) in &files {
+        let path = root.join(file_name);
+        let fixed = apply_edits(&read(&path)?, edits)
+            .with_context(|| format!("could not fix {file_name}"))?;
+        std::fs::write(&path, fixed).with_context(|| format!("could not write {file_name}"))?;
+    }
+    Ok(files.len())
+}
+
+fn by_file(edits: &[Edit]) -> BTreeMap<String, Vec<&Edit>> {
+    let mut result: BTreeMap<String, Vec<&Edit>> = BTreeMap::new();
+    for edit in edits {
+        result.entry(edit.file_name.clone()).or_default().push(edit);
+    }
+    result
+}
+
+fn read(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn edit(byte_range: Range<usize>, replacement: &str) -> Edit {
+        Edit {
+            file_name: "src/main.rs".to_string(),
+            byte_range,
+            replacement: replacement.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_edits() {
+        let diagnostic: Diagnostic = serde_json::from_str(
+            r#"{"message":"unnecessary parentheses","code":{"code":"clippy::double_parens","explanation":null},"level":"warning","spans":[],"children":[
+{"message":"remove them","code":null,"level":"help","spans":[{"file_name":"src/main.rs","byte_start":30,"byte_end":35,"line_start":2,"line_end":2,"column_start":20,"column_end":25,"is_primary":true,"suggested_replacement":"0","suggestion_applicability":"MachineApplicable"}],"children":[]},
+{"message":"or this","code":null,"level":"help","spans":[{"file_name":"src/main.rs","byte_start":30,"byte_end":35,"line_start":2,"line_end":2,"column_start":20,"column_end":25,"is_primary":true,"suggested_replacement":"(0)","suggestion_applicability":"MaybeIncorrect"}],"children":[]}
+]}"#,
+        )
+        .unwrap();
+        assert_eq!(edits(&diagnostic), [edit(30..35, "0")]);
+    }
+
+    #[test]
+    fn test_edits_alternatives() {
+        // Rustc offers two ways to fix the same code, and only the first one is applied
+        let diagnostic: Diagnostic = serde_json::from_str(
+            r#"{"message":"unused variable: `a`","code":{"code":"unused_variables","explanation":null},"level":"warning","spans":[],"children":[
+{"message":"if this is intentional, prefix it with an underscore","code":null,"level":"help","spans":[{"file_name":"src/main.rs","byte_start":4,"byte_end":5,"line_start":1,"line_end":1,"column_start":5,"column_end":6,"is_primary":true,"suggested_replacement":"_a","suggestion_applicability":"MachineApplicable"}],"children":[]},
+{"message":"or remove it","code":null,"level":"help","spans":[{"file_name":"src/main.rs","byte_start":0,"byte_end":14,"line_start":1,"line_end":1,"column_start":1,"column_end":15,"is_primary":true,"suggested_replacement":"","suggestion_applicability":"MachineApplicable"},{"file_name":"src/main.rs","byte_start":20,"byte_end":21,"line_start":2,"line_end":2,"column_start":1,"column_end":2,"is_primary":false,"suggested_replacement":"","suggestion_applicability":"MachineApplicable"}],"children":[]}
+]}"#,
+        )
+        .unwrap();
+        assert_eq!(edits(&diagnostic), [edit(4..5, "_a")]);
+    }
+
+    #[test]
+    fn test_apply_from_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "let a = ((0));").unwrap();
+        assert_eq!(apply(&[edit(8..13, "0")], root).unwrap(), 1);
+        let fixed = std::fs::read_to_string(root.join("src/main.rs")).unwrap();
+        assert_eq!(fixed, "let a = 0;");
+    }
+
+    #[rstest]
+    #[case(&[], "let a = ((0));")]
+    #[case(&[edit(8..13, "0")], "let a = 0;")]
+    #[case(&[edit(9..10, ""), edit(11..12, ""), edit(4..5, "_a")], "let _a = (0);")]
+    fn test_apply_edits(#[case] edits: &[Edit], #[case] expected: &str) {
+        let edits: Vec<&Edit> = edits.iter().collect();
+        assert_eq!(apply_edits("let a = ((0));", &edits).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(&[edit(8..13, "0"), edit(9..12, "0")])]
+    #[case(&[edit(8..20, "0")])]
+    fn test_apply_edits_invalid(#[case] edits: &[Edit]) {
+        let edits: Vec<&Edit> = edits.iter().collect();
+        assert!(apply_edits("let a = ((0));", &edits).is_err());
+    }
+
+    #[test]
+    fn test_overlaps() {
+        assert!(edit(8..13, "0").overlaps(&edit(9..12, "0")));
+        assert!(!edit(8..13, "0").overlaps(&edit(13..14, "")));
+        // Insertions at the same place conflict
+        assert!(edit(8..8, "a").overlaps(&edit(8..8, "b")));
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(
+            diff(
+                "fn main() {\n    let a = ((0));\n}\n",
+                "fn main() {\n    let a = 0;\n}\n"
+            ),
+            "   2 -     let a = ((0));\n   2 +     let a = 0;\n"
+        );
+        assert_eq!(diff("a\n", "a\n"), "");
+    }
+}
@@ -1,166 +1,231 @@
 #![doc = include_str!("../README.md")]
 
+use crate::character::Character;
 use crate::clippit_art::ClippyArt;
-use regex::{Captures, Regex, Replacer};
+use crate::diagnostic::Diagnostic;
+use crate::message::Message;
+pub use crate::rewriter::{MessageStream, Part, Rewriter};
 use std::borrow::Cow;
 use std::io::Write;
-use std::mem::swap;
+use std::sync::LazyLock;
 use terminal_size::terminal_size;
 
+pub mod ansi;
+pub mod character;
 pub mod clippit_art;
-
+pub mod diagnostic;
+pub mod dismissed;
+pub mod explain;
+pub mod fix;
+pub mod locale;
+pub mod message;
+pub mod options;
+pub mod personality;
+pub mod render;
+mod rewriter;
+pub mod rules;
+pub mod workspace;
+
+/// Writes Clippit saying the output of cargo or rustc, which can be human-readable or JSON.
 pub fn output<Writer>(input: &str, output: &mut Writer) -> std::io::Result<()>
-    where
-        Writer: Write,
+where
+    Writer: Write,
 {
-    let width = u16::min(terminal_size().map(|a| a.0.0).unwrap_or(100), 120);
-    let mut clippy = ClippyArt::new(width);
+    write_clippy(&BUILTIN_REWRITER.replace_output(input), output)
+}
 
-    clippy.add_str(&replace_words(input));
-    clippy.finish();
-    for s in clippy {
-        write!(output, "{s}")?;
-    }
+/// Same as `output()` but takes the records from `cargo clippy --message-format=json`.
+pub fn output_messages<Writer>(messages: &[Message], output: &mut Writer) -> std::io::Result<()>
+where
+    Writer: Write,
+{
+    write_clippy(&replace_messages(messages), output)
+}
 
-    Ok(())
+/// Writes Clippit saying `s`, without rephrasing it.
+pub fn write_clippy<Writer>(s: &str, output: &mut Writer) -> std::io::Result<()>
+where
+    Writer: Write,
+{
+    let mut clippy = ClippyWriter::new(output);
+    clippy.write_str(s)?;
+    clippy.finish()
 }
 
-/// Replaces words in given string to sound like Clippit.
-pub fn replace_words(s: &str) -> String {
-    let no_warnings = !s.contains("warning:") && !s.contains("error:");
-
-    let mut result = s.to_string();
-    regex_replace_once(&mut result, "^    Checking(.*)", "I'm checking$1...");
-
-    regex_replace_once(
-        &mut result,
-        r"(?m)^error: aborting due to previous error.*",
-        "Sorry, but I cannot continue compiling with that error.",
-    );
-
-    regex_replace_once(
-        &mut result,
-        r"(?m)^error: aborting due to \d* previous errors; \d* warnings emitted",
-        "Sorry, but you have too many errors in your code.",
-    );
-
-    regex_replace_once(
-        &mut result,
-        r"(?m)^error: could not compile (.*) due to.*",
-        "Let's fix $1!",
-    );
-
-    regex_replace_once(
-        &mut result,
-        "    Finished(.*)\n?$",
-        "I finished compiling$1.\n",
-    );
-
-    regex_replace(
-        &mut result,
-        r"(?m)^error: expected (.*), found (.*)",
-        "The syntax is wrong because I expected $1 but I found $2.",
-    );
-
-    regex_replace(
-        &mut result,
-        r"(?m)^error\[E0597\]: `(.*)` does not live long enough$",
-        "Oops! It looks like the variable with lifetime `$1` is dropped before it is used.",
-    );
-
-    regex_replace(
-        &mut result,
-        r"(?m)^error\[\S+\]: expected (.*), found (.*)",
-        "Oops! I expected $1, but I found $2.",
-    );
-
-    regex_replace(&mut result, r"(?m)^error\[\S+\]:(.*)", "Oops!$1.");
-
-    regex_replace(&mut result, r"(?m)^help: use (.*)", "Psst... use $1.");
-
-    regex_replace(
-        &mut result,
-        r"(?m)^(warning|error):(.*)",
-        |caps: &Captures| {
-            if let Some(s) = caps[2].strip_suffix(" warnings emitted") {
-                "You have".to_string() + s + " issues in your code."
-            } else if let Some(s) = caps[2].strip_suffix(" warning emitted") {
-                "You have".to_string() + s + " issue in your code."
-            } else if caps[2].contains('.') {
-                "It looks like this could be improved because".to_string() + &caps[2] + "."
-            } else {
-                "Hmmm...".to_string() + &caps[2] + "."
+/// Writes Clippit's speech bubble while text is added, e.g. while cargo is still running.
+///
+/// Complete lines are written and flushed by `write_str()`. The bottom of the speech bubble is
+/// written by `finish()`. After `split_bubbles()`, `write_parts()` says each diagnostic in its own
+/// speech bubble.
+pub struct ClippyWriter<'a, Writer>
+where
+    Writer: Write,
+{
+    /// `None` if only the text is written, without Clippit and the speech bubble
+    clippy: Option<ClippyArt>,
+    output: &'a mut Writer,
+    is_empty: bool,
+    colors: bool,
+    character: Character,
+    width: u16,
+    /// `Some` if each diagnostic gets its own speech bubble. See `split_bubbles()`.
+    redraw_every: Option<usize>,
+    /// Number of speech bubbles so far, including the current one
+    bubbles: usize,
+    is_bubble_empty: bool,
+    is_diagnostic_bubble: bool,
+}
+
+impl<'a, Writer> ClippyWriter<'a, Writer>
+where
+    Writer: Write,
+{
+    /// Writes a speech bubble that fits the terminal, but is at most 120 characters wide.
+    pub fn new(output: &'a mut Writer) -> Self {
+        Self::with_width(output, default_width())
+    }
+
+    pub fn with_width(output: &'a mut Writer, width: u16) -> Self {
+        Self::with_character(output, width, &Character::default())
+    }
+
+    /// Writes `character` saying the text in a speech bubble that is `width` columns wide.
+    pub fn with_character(output: &'a mut Writer, width: u16, character: &Character) -> Self {
+        Self {
+            clippy: Some(ClippyArt::with_character(width, character)),
+            output,
+            is_empty: true,
+            colors: true,
+            character: character.clone(),
+            width,
+            redraw_every: None,
+            bubbles: 1,
+            is_bubble_empty: true,
+            is_diagnostic_bubble: false,
+        }
+    }
+
+    /// Only writes the text, without Clippit and the speech bubble.
+    pub fn plain(output: &'a mut Writer) -> Self {
+        Self {
+            clippy: None,
+            ..Self::with_width(output, 0)
+        }
+    }
+
+    /// Returns `true` if no text has been written yet.
+    pub fn is_empty(&self) -> bool {
+        self.is_empty
+    }
+
+    /// Removes the colors from the text if `colors` is `false`.
+    pub fn set_colors(&mut self, colors: bool) {
+        self.colors = colors;
+    }
+
+    /// Says each diagnostic that is written with `write_parts()` in its own speech bubble, and the
+    /// lines between diagnostics, such as the summary at the end, in another one. The character is
+    /// drawn above every `redraw_every`th speech bubble, or only above the first one if
+    /// `redraw_every` is 0.
+    pub fn split_bubbles(&mut self, redraw_every: usize) {
+        self.redraw_every = Some(redraw_every);
+    }
+
+    /// Writes the parts of the rewritten output.
+    pub fn write_parts(&mut self, parts: &[Part]) -> std::io::Result<()> {
+        for part in parts {
+            // Empty lines are not said, so they do not need a speech bubble
+            if self.redraw_every.is_some() && !part.text().trim().is_empty() {
+                let is_diagnostic = matches!(part, Part::Diagnostic(_));
+                if !self.is_bubble_empty && (is_diagnostic || self.is_diagnostic_bubble) {
+                    self.next_bubble()?;
+                }
+                self.is_diagnostic_bubble = is_diagnostic;
             }
-        },
-    );
+            self.write_str(part.text())?;
+        }
+        Ok(())
+    }
 
-    regex_replace(&mut result, r"(?m)^(  = )?note:(.*)", |caps: &Captures| {
-        let mut result = if caps[0].starts_with("  ") {
-            "  Note:"
+    pub fn write_str(&mut self, s: &str) -> std::io::Result<()> {
+        if s.is_empty() {
+            return Ok(());
+        }
+        self.is_empty = false;
+        self.is_bubble_empty &= s.trim().is_empty();
+        let s = if self.colors {
+            Cow::Borrowed(s)
         } else {
-            "Note:"
+            ansi::strip(s)
+        };
+        match &mut self.clippy {
+            Some(clippy) => clippy.add_str(&s),
+            None => write!(self.output, "{s}")?,
         }
-            .to_string();
-        result.push_str(&caps[2]);
-        if !caps[2].ends_with('.') && !caps[2].ends_with('?') {
-            result.push('.')
+        self.flush()
+    }
+
+    pub fn finish(mut self) -> std::io::Result<()> {
+        if let Some(clippy) = &mut self.clippy {
+            clippy.finish();
         }
-        result
-    });
-
-    regex_replace(
-        &mut result,
-        r"(?m)^  = help: for further information visit (.*)",
-        "  Would you like some help with this? Visit\n  $1.",
-    );
-
-    regex_replace(&mut result, r"(?m)^  = help:(.*)", |caps: &Captures| {
-        let mut result = "  Hint:".to_string() + &caps[1];
-        if !caps[1].ends_with('?') {
-            result.push('.')
+        self.flush()
+    }
+
+    /// Finishes the current speech bubble and starts another one.
+    fn next_bubble(&mut self) -> std::io::Result<()> {
+        let Some(clippy) = &mut self.clippy else {
+            return Ok(());
+        };
+        clippy.finish();
+        self.flush()?;
+
+        let redraw_every = self.redraw_every.unwrap_or_default();
+        let redraw = redraw_every > 0 && self.bubbles % redraw_every == 0;
+        self.clippy = Some(if redraw {
+            ClippyArt::with_character(self.width, &self.character)
+        } else {
+            ClippyArt::without_art(self.width, &self.character)
+        });
+        self.bubbles += 1;
+        self.is_bubble_empty = true;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        for s in self.clippy.iter_mut().flatten() {
+            write!(self.output, "{s}")?;
         }
-        result
-    });
-
-    // "^^ help: if this is intentional..."
-    regex_replace(&mut result, r"(?m)^  \|( *)(\^+) help: if", "  |$1$2 If");
-
-    // "^ help: remove this semicolon"
-    regex_replace(
-        &mut result,
-        r"(?m)^  \|( *| \|_+)(\^+) help:",
-        "  |$1$2 You should",
-    );
-
-    regex_replace(
-        &mut result,
-        r"thread 'main' panicked at ",
-        "#$@#$@#$!#$%!@#$ !INTERNAL ERROR! PLEASE REFER TO OWNERS MANUAL\n",
-    );
-
-    if no_warnings {
-        result.push_str("Woohoo, no warnings!\n");
+        self.output.flush()
     }
+}
 
-    result
+/// Returns the width of the terminal, but at most 120 columns.
+pub fn default_width() -> u16 {
+    u16::min(terminal_size().map(|a| a.0 .0).unwrap_or(100), 120)
 }
 
-fn regex_replace<R>(str: &mut String, regex: &str, replacement: R)
-    where
-        R: Replacer,
-{
-    if let Cow::Owned(mut s) = Regex::new(regex).unwrap().replace_all(str, replacement) {
-        swap(str, &mut s);
-    }
+/// The built-in rules are only compiled once.
+static BUILTIN_REWRITER: LazyLock<Rewriter> = LazyLock::new(Rewriter::default);
+
+/// Replaces words in given string to sound like Clippit.
+///
+/// Uses the built-in rules. Use `Rewriter` to rephrase with other rules.
+pub fn replace_words(s: &str) -> String {
+    BUILTIN_REWRITER.replace_words(s)
 }
 
-fn regex_replace_once<R>(str: &mut String, regex: &str, replacement: R)
-    where
-        R: Replacer,
-{
-    if let Cow::Owned(mut s) = Regex::new(regex).unwrap().replace(str, replacement) {
-        swap(str, &mut s);
-    }
+/// Phrases the records from `cargo clippy --message-format=json` like Clippit.
+///
+/// Unlike `replace_words()`, diagnostics are grouped by their fields instead of by how rustc
+/// formats its messages.
+pub fn replace_messages(messages: &[Message]) -> String {
+    BUILTIN_REWRITER.replace_messages(messages)
+}
+
+/// Phrases diagnostics like Clippit, e.g. after filtering the output of `diagnostic::parse_human()`.
+pub fn replace_diagnostics(diagnostics: &[Diagnostic]) -> String {
+    BUILTIN_REWRITER.replace_diagnostics(diagnostics)
 }
 
 #[cfg(test)]
@@ -592,7 +657,7 @@ Let's fix `playground` (bin "playground")!
     }
      */
     #[case(
-    r#"    Checking playground v0.0.1 (/playground)
+        r#"    Checking playground v0.0.1 (/playground)
 error[E0423]: expected function, found macro `println`
  --> src/main.rs:2:5
   |
@@ -607,7 +672,7 @@ help: use `!` to invoke the macro
 For more information about this error, try `rustc --explain E0423`.
 error: could not compile `playground` (bin "playground") due to previous error
 "#,
-    r#"I'm checking playground v0.0.1 (/playground)...
+        r#"I'm checking playground v0.0.1 (/playground)...
 Oops! I expected function, but I found macro `println`.
  --> src/main.rs:2:5
   |
@@ -633,9 +698,168 @@ note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
 'Usage of `--fix` requires `-Z unstable-options`', src/tools/clippy/src/main.rs:92:13
 Note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace.
 Woohoo, no warnings!
+"#
+    )]
+    // 12
+    #[case(
+        r#"   Compiling playground v0.0.1 (/playground)
+    Finished test [unoptimized + debuginfo] target(s) in 0.52s
+     Running unittests src/lib.rs (target/debug/deps/playground-1f2c3d4e5f6a7b8c)
+   Doc-tests playground
+"#,
+        r#"I'm compiling playground v0.0.1 (/playground)...
+I finished compiling test [unoptimized + debuginfo] target(s) in 0.52s.
+I'm running unittests src/lib.rs (target/debug/deps/playground-1f2c3d4e5f6a7b8c)...
+I'm testing the examples in the documentation of playground...
+Woohoo, no warnings!
+"#
+    )]
+    // 13
+    #[case(
+        r#" Documenting playground v0.0.1 (/playground)
+    Finished dev [unoptimized + debuginfo] target(s) in 0.31s
+   Generated /playground/target/doc/playground/index.html
+"#,
+        r#"I'm writing the documentation for playground v0.0.1 (/playground)...
+I finished compiling dev [unoptimized + debuginfo] target(s) in 0.31s.
+Your documentation is ready: /playground/target/doc/playground/index.html
+Woohoo, no warnings!
 "#
     )]
     fn test_replace_words(#[case] input: &str, #[case] expected: &str) {
         assert_eq!(replace_words(input), expected);
     }
+
+    #[rstest]
+    // 1
+    #[case(
+        r#"{"reason":"compiler-artifact","package_id":"path+file:///playground#0.0.1","target":{"kind":["bin"],"name":"playground"},"fresh":false}
+{"reason":"build-finished","success":true}
+"#,
+        // Expected
+        r#"I'm checking playground v0.0.1 (/playground)...
+I finished compiling.
+Woohoo, no warnings!
+"#
+    )]
+    // 2
+    #[case(
+        r#"{"reason":"compiler-message","package_id":"path+file:///playground#0.0.1","target":{"kind":["bin"],"name":"playground"},"message":{"rendered":"warning: unnecessary trailing semicolon\n --> src/main.rs:2:27\n  |\n2 |     println!(\"{}\", ((0)));;\n  |                           ^ help: remove this semicolon\n  |\n  = note: `#[warn(redundant_semicolons)]` on by default\n\n","children":[{"children":[],"code":null,"level":"note","message":"`#[warn(redundant_semicolons)]` on by default","rendered":null,"spans":[]},{"children":[],"code":null,"level":"help","message":"remove this semicolon","rendered":null,"spans":[{"byte_end":43,"byte_start":42,"column_end":28,"column_start":27,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":"","suggestion_applicability":"MaybeIncorrect","text":[]}]}],"level":"warning","message":"unnecessary trailing semicolon","spans":[{"byte_end":43,"byte_start":42,"column_end":28,"column_start":27,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}],"code":{"code":"redundant_semicolons","explanation":null}}}
+{"reason":"compiler-artifact","package_id":"path+file:///playground#0.0.1","target":{"kind":["bin"],"name":"playground"},"fresh":false}
+{"reason":"build-finished","success":true}
+"#,
+        // Expected
+        r#"I'm checking playground v0.0.1 (/playground)...
+Hmmm... unnecessary trailing semicolon.
+ --> src/main.rs:2:27
+  |
+2 |     println!("{}", ((0)));;
+  |                           ^ You should remove this semicolon
+  |
+  Note: `#[warn(redundant_semicolons)]` on by default.
+
+Hmmm... `playground` (bin "playground") generated 1 warning.
+I finished compiling.
+"#
+    )]
+    // 3
+    #[case(
+        r#"{"reason":"compiler-message","package_id":"path+file:///playground#0.0.1","target":{"kind":["bin"],"name":"playground"},"message":{"rendered":"error[E0423]: expected function, found macro `println`\n --> src/main.rs:2:5\n  |\n2 |     println();\n  |     ^^^^^^^ not a function\n  |\nhelp: use `!` to invoke the macro\n  |\n2 |     println!();\n  |            +\n\n","children":[{"children":[],"code":null,"level":"help","message":"use `!` to invoke the macro","rendered":null,"spans":[{"byte_end":23,"byte_start":23,"column_end":12,"column_start":12,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":"!","suggestion_applicability":"MachineApplicable","text":[]}]}],"level":"error","message":"expected function, found macro `println`","spans":[{"byte_end":23,"byte_start":16,"column_end":12,"column_start":5,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":"not a function","line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}],"code":{"code":"E0423","explanation":"An identifier was used like a function name or a value was expected and\nthe identifier exists but it belongs to a different namespace.\n"}}}
+{"reason":"build-finished","success":false}
+"#,
+        // Expected
+        r#"I'm checking playground v0.0.1 (/playground)...
+Oops! I expected function, but I found macro `println`.
+ --> src/main.rs:2:5
+  |
+2 |     println();
+  |     ^^^^^^^ not a function
+  |
+Psst... use `!` to invoke the macro.
+  |
+2 |     println!();
+  |            +
+
+Let's fix `playground` (bin "playground")!
+"#
+    )]
+    fn test_replace_messages(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(replace_messages(&message::parse_messages(input)), expected);
+    }
+
+    #[test]
+    fn test_clippy_writer() {
+        let mut output: Vec<u8> = Vec::new();
+        let mut clippy = ClippyWriter::new(&mut output);
+        clippy.write_str("I'm checking playground...\n").unwrap();
+        clippy.finish().unwrap();
+        let finished = String::from_utf8(output).unwrap();
+
+        let mut output: Vec<u8> = Vec::new();
+        let mut clippy = ClippyWriter::new(&mut output);
+        clippy.write_str("I'm checking playground...\n").unwrap();
+        let streamed = String::from_utf8(clippy.output.clone()).unwrap();
+
+        // Only the bottom of the speech bubble is left for `finish()`
+        assert!(streamed.contains("| I'm checking playground..."));
+        let bottom = finished.strip_prefix(&streamed).unwrap();
+        assert!(bottom.starts_with("\\_"));
+        assert!(bottom.ends_with("_/\n"));
+        assert_eq!(bottom.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_split_bubbles() {
+        let character = Character {
+            name: String::new(),
+            art: " o\n/|\\\n /\\\n".to_string(),
+            tail: None,
+            bubble: character::BubbleStyle::Ascii,
+        };
+        let input = "    Checking playground v0.0.1 (/playground)
+warning: unused variable: `a`
+ --> src/main.rs:2:9
+
+warning: unused variable: `b`
+ --> src/main.rs:3:9
+
+    Finished dev [unoptimized + debuginfo] target(s) in 0.30s
+";
+        let mut output: Vec<u8> = Vec::new();
+        let mut clippy = ClippyWriter::with_character(&mut output, 40, &character);
+        clippy.split_bubbles(2);
+        clippy
+            .write_parts(&Rewriter::default().replace_output_parts(input))
+            .unwrap();
+        clippy.finish().unwrap();
+
+        // The character is drawn above the 1st and the 3rd speech bubble
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            r#" o
+/|\
+ /\
+.  ------------------------------------.
+| I'm checking playground v0.0.1 (/    |
+| playground)...                       |
+'--------------------------------------'
+.--------------------------------------.
+| Hmmm... unused variable: `a`.        |
+|  --> src/main.rs:2:9                 |
+'--------------------------------------'
+ o
+/|\
+ /\
+.  ------------------------------------.
+| Hmmm... unused variable: `b`.        |
+|  --> src/main.rs:3:9                 |
+'--------------------------------------'
+.--------------------------------------.
+| I finished compiling dev             |
+| [unoptimized + debuginfo] target(s)  |
+| in 0.30s.                            |
+'--------------------------------------'
+"#
+        );
+    }
 }
@@ -0,0 +1,222 @@
+//! Clippit's phrases in other languages.
+//!
+//! The catalogs are in `src/locales/<language>.toml`. `[rules]` translates the `replacement` of the
+//! built-in rules by their `id`, and `[messages]` has the phrases that are not rules, such as
+//! "Woohoo, no warnings!". Phrases that are missing in a language are said in English.
+
+use crate::personality::Personality;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// The languages that Clippit speaks, by the language code that `LANG` starts with.
+pub const LANGUAGES: &[&str] = &["en", "ja", "de"];
+
+const ENGLISH: &str = include_str!("locales/en.toml");
+
+fn catalog_source(language: &str) -> &'static str {
+    match language {
+        "ja" => include_str!("locales/ja.toml"),
+        "de" => include_str!("locales/de.toml"),
+        _ => ENGLISH,
+    }
+}
+
+/// A phrase, which can have a different form for a `{count}` of one.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Message {
+    Text(String),
+    Plural { one: String, other: String },
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CatalogFile {
+    #[serde(default)]
+    rules: HashMap<String, String>,
+    #[serde(default)]
+    messages: HashMap<String, Message>,
+}
+
+/// The phrases of one language.
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    language: &'static str,
+    rules: HashMap<String, String>,
+    messages: HashMap<String, Message>,
+}
+
+impl Catalog {
+    /// Returns the catalog for `language`, which can be a language code such as `ja` or a locale
+    /// such as `de_DE.UTF-8`. Unknown languages are English.
+    pub fn new(language: &str) -> Self {
+        let language = language_code(language).unwrap_or("en");
+        let english: CatalogFile = toml::from_str(ENGLISH).unwrap();
+        let translation: CatalogFile = toml::from_str(catalog_source(language)).unwrap();
+
+        let mut messages = english.messages;
+        messages.extend(translation.messages);
+        Self {
+            language,
+            rules: translation.rules,
+            messages,
+        }
+    }
+
+    /// Returns the catalog for the language of the user's locale, from `LC_ALL`, `LC_MESSAGES` or
+    /// `LANG`.
+    pub fn from_env() -> Self {
+        Self::new(&env_language().unwrap_or_default())
+    }
+
+    /// Replaces phrases with the ones of `personality`, if it speaks the language of the catalog.
+    /// Otherwise the phrases stay the classic ones, because they would be in another language.
+    pub fn with_personality(mut self, personality: &Personality) -> Self {
+        if personality.speaks(self.language) {
+            self.rules.extend(personality.rules.clone());
+            self.messages.extend(personality.messages.clone());
+        }
+        self
+    }
+
+    /// Returns the language code, e.g. `ja`.
+    pub fn language(&self) -> &'static str {
+        self.language
+    }
+
+    /// Returns the translated replacement of the built-in rule `id`.
+    pub fn rule_replacement(&self, id: &str) -> Option<&str> {
+        self.rules.get(id).map(String::as_str)
+    }
+
+    /// Returns the message `key` with each `{name}` replaced by its value in `args`.
+    pub fn text(&self, key: &str, args: &[(&str, &str)]) -> String {
+        match self.messages.get(key) {
+            Some(Message::Text(text) | Message::Plural { other: text, .. }) => format(text, args),
+            None => key.to_string(),
+        }
+    }
+
+    /// Returns the form of the message `key` for `count`, with `{count}` and each `{name}` in
+    /// `args` replaced by their values.
+    pub fn plural(&self, key: &str, count: usize, args: &[(&str, &str)]) -> String {
+        let count_str = count.to_string();
+        let mut args = args.to_vec();
+        args.push(("count", &count_str));
+        match self.messages.get(key) {
+            Some(Message::Plural { one, .. }) if count == 1 => format(one, &args),
+            Some(Message::Text(text) | Message::Plural { other: text, .. }) => format(text, &args),
+            None => key.to_string(),
+        }
+    }
+}
+
+impl Default for Catalog {
+    /// Returns the English catalog.
+    fn default() -> Self {
+        Self::new("en")
+    }
+}
+
+/// Returns the language of the user's locale, e.g. `ja_JP.UTF-8`. The first of `LC_ALL`,
+/// `LC_MESSAGES` and `LANG` that is set wins, like in gettext.
+pub fn env_language() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// Returns the code of a language that Clippit speaks, e.g. `de` for `de_DE.UTF-8`. The `C` and
+/// `POSIX` locales are English.
+fn language_code(locale: &str) -> Option<&'static str> {
+    let code = locale.split(['_', '-', '.', '@']).next()?.to_lowercase();
+    LANGUAGES.iter().copied().find(|language| *language == code)
+}
+
+fn format(text: &str, args: &[(&str, &str)]) -> String {
+    let mut result = text.to_string();
+    for (name, value) in args {
+        result = result.replace(&format!("{{{name}}}"), value);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::builtin_rules;
+    use regex::Regex;
+    use rstest::rstest;
+    use std::collections::BTreeSet;
+
+    #[rstest]
+    #[case("ja", "ja")]
+    #[case("ja_JP.UTF-8", "ja")]
+    #[case("de-AT", "de")]
+    #[case("DE_de", "de")]
+    #[case("C", "en")]
+    #[case("POSIX", "en")]
+    #[case("fr_FR.UTF-8", "en")]
+    #[case("", "en")]
+    fn test_language(#[case] locale: &str, #[case] expected: &str) {
+        assert_eq!(Catalog::new(locale).language(), expected);
+    }
+
+    #[test]
+    fn test_messages() {
+        let english = Catalog::default();
+        assert_eq!(english.text("no-warnings", &[]), "Woohoo, no warnings!");
+        assert_eq!(
+            english.plural("applied", 1, &[("files", "1 file")]),
+            "I applied 1 suggestion to 1 file."
+        );
+        assert_eq!(
+            english.plural("applied", 2, &[("files", "1 file")]),
+            "I applied 2 suggestions to 1 file."
+        );
+
+        let japanese = Catalog::new("ja");
+        assert_eq!(
+            japanese.text("no-warnings", &[]),
+            "やったー、警告はありません！"
+        );
+        assert_eq!(
+            japanese.plural("dismissed-hidden", 1, &[]),
+            "非表示にしたヒントが 1 件ありました。"
+        );
+        // Missing phrases are said in English
+        assert_eq!(
+            japanese.text("lint-does", &[("lint", "clippy::a"), ("what", "checks.")]),
+            "Did you know? `clippy::a` checks."
+        );
+        assert_eq!(english.rule_replacement("warning"), None);
+        assert_eq!(
+            Catalog::new("de").rule_replacement("warning"),
+            Some("Hmmm... $1.")
+        );
+    }
+
+    /// Each translation only has phrases that exist in English, and uses the same captures.
+    #[rstest]
+    fn test_catalogs(#[values("ja", "de")] language: &str) {
+        let english: CatalogFile = toml::from_str(ENGLISH).unwrap();
+        let translation: CatalogFile = toml::from_str(catalog_source(language)).unwrap();
+        for key in translation.messages.keys() {
+            assert!(english.messages.contains_key(key), "unknown message {key}");
+        }
+
+        let captures = |s: &str| -> BTreeSet<String> {
+            let regex = Regex::new(r"\$(\d+|\{\d+\})").unwrap();
+            regex
+                .captures_iter(&s.replace("$$", ""))
+                .map(|c| c[1].trim_matches(['{', '}']).to_string())
+                .collect()
+        };
+        let rules = builtin_rules();
+        for (id, replacement) in &translation.rules {
+            let rule = rules.iter().find(|rule| &rule.id == id);
+            let rule = rule.unwrap_or_else(|| panic!("unknown rule {id}"));
+            assert_eq!(captures(replacement), captures(&rule.replacement), "{id}");
+        }
+    }
+}
@@ -1,39 +1,675 @@
 #![warn(clippy::pedantic)]
 
-use anyhow::Result;
-use clippit::output;
+use anyhow::{Context, Result};
+use clippit::character::{configured_character, load_character, BubbleStyle, Character};
+use clippit::diagnostic::{
+    add_crate, is_error_code, parse_crates, push_crate_diagnostic, CrateDiagnostics, Diagnostic,
+    Level,
+};
+use clippit::dismissed::{Dismissed, DISMISSED_PATH};
+use clippit::explain::{explain_lint, ErrorExplainer};
+use clippit::fix::{self, Edit};
+use clippit::locale::Catalog;
+use clippit::message::{is_local_package, package_name, parse_messages, Message};
+use clippit::options::{
+    parse_args, DismissedCommand, Format, JunitWarnings, Layout, Options, Theme, HELP,
+};
+use clippit::personality::{configured_personality, load_personality};
+use clippit::render;
+use clippit::workspace::workspace_root;
+use clippit::{default_width, ClippyWriter, Part, Rewriter};
 use std::env::args;
-use std::io::Write;
-use std::process::Command;
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::sync::OnceLock;
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-/// Use -v to see the `cargo clippy` command and output.
+/// See `clippit --help` for the options.
 fn main() -> Result<()> {
-    let args: Vec<String> = args().skip(1).collect();
-    std::process::exit(run(args, &mut std::io::stderr())?);
+    let options = parse_args(args().skip(1))?;
+    if options.help {
+        print!("{HELP}");
+        return Ok(());
+    }
+    if options.version {
+        println!("clippit {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+    if let Some(code) = &options.explain {
+        let catalog = catalog(&options)?;
+        let explanation = if is_error_code(code) {
+            ErrorExplainer::default().explain(code, &catalog)
+        } else {
+            explain_lint(code, &catalog)
+        };
+        let explanation =
+            explanation.with_context(|| catalog.text("unknown-code", &[("code", code)]))?;
+        say(&options, &mut std::io::stderr(), &explanation)?;
+        return Ok(());
+    }
+    if let Some(command) = options.dismissed {
+        let mut dismissed = Dismissed::load(&root(&options).join(DISMISSED_PATH))?;
+        match command {
+            DismissedCommand::List => dismissed.codes().for_each(|code| println!("{code}")),
+            DismissedCommand::Reset => dismissed.reset()?,
+            DismissedCommand::Export => print!("{}", dismissed.export_lints()),
+        }
+        return Ok(());
+    }
+
+    let rewriter = rewriter(&options)?;
+    let status = match options.format {
+        Format::Text => run(&options, &rewriter, Path::new("."), &mut std::io::stderr())?.0,
+        Format::Html
+        | Format::Svg
+        | Format::Markdown
+        | Format::Github
+        | Format::Sarif
+        | Format::Junit => {
+            // The page, the image or the log is written when all of the output is there
+            let mut output = Vec::new();
+            let (status, crates) = run(&options, &rewriter, Path::new("."), &mut output)?;
+            let text = String::from_utf8_lossy(&output);
+            print!("{}", render(&options, &rewriter, &text, &crates)?);
+            if options.format == Format::Github {
+                let diagnostics = all_diagnostics(&crates);
+                write_step_summary(&options, &rewriter, &text, &diagnostics)?;
+            }
+            status
+        }
+    };
+    std::process::exit(status);
 }
 
-fn run<Writer>(mut args: Vec<String>, writer: &mut Writer) -> Result<i32>
+/// Returns the rewriter with the rule files and the options.
+fn rewriter(options: &Options) -> Result<Rewriter> {
+    let mut rewriter = Rewriter::load(options.config.as_deref())?;
+    rewriter.set_quiet(options.quiet);
+    rewriter.set_dismissed(Dismissed::load(&root(options).join(DISMISSED_PATH))?);
+    rewriter.set_explain_lints(options.explain_lints);
+    rewriter.set_explain_errors(options.explain_errors);
+    rewriter.set_catalog(catalog(options)?);
+    // The other formats are reports that are compared between runs, e.g. in CI
+    match options.seed {
+        Some(seed) => rewriter.set_seed(seed),
+        None if options.format == Format::Text => rewriter.set_seed(random_seed()),
+        None => {}
+    }
+    Ok(rewriter)
+}
+
+/// Returns the root of the workspace, which is only looked up with `cargo metadata` once. Saved
+/// output may not be from this workspace, so its root is the current directory.
+fn root(options: &Options) -> &'static Path {
+    static ROOT: OnceLock<PathBuf> = OnceLock::new();
+    ROOT.get_or_init(|| {
+        if options.input.is_some() {
+            PathBuf::from(".")
+        } else {
+            workspace_root()
+        }
+    })
+}
+
+/// Phrases the output of cargo run in `dir`, or of the input from `--input`. Returns the exit
+/// status and the diagnostics by crate.
+fn run<Writer>(
+    options: &Options,
+    rewriter: &Rewriter,
+    dir: &Path,
+    writer: &mut Writer,
+) -> Result<(i32, Vec<CrateDiagnostics>)>
 where
     Writer: Write,
 {
-    args.insert(0, "clippy".to_string());
-
-    let is_verbose = args.iter().any(|arg| arg == "-v" || arg == "--verbose");
+    match options.input.as_deref() {
+        Some("-") => return filter(rewriter, options, std::io::stdin().lock(), writer),
+        Some(path) => {
+            let file = File::open(path).with_context(|| format!("could not open {path}"))?;
+            return filter(rewriter, options, file, writer);
+        }
+        None => {}
+    }
 
     let mut command = Command::new("cargo");
 
-    command.args(args);
+    command.args(cargo_args(options)).current_dir(dir);
+    command.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    if options.verbose {
+        eprintln!("cargo command: {command:?}");
+    }
+
+    let mut child = command.spawn()?;
 
-    let clippy_output = command.output()?;
-    let clippy_string = std::str::from_utf8(clippy_output.stderr.as_ref())?;
+    // Both pipes have to be read while cargo runs so that cargo does not block on a full pipe
+    let (sender, receiver) = mpsc::channel();
+    let stdout_thread = read_lines(child.stdout.take().unwrap(), sender.clone(), Line::Stdout);
+    let stderr_thread = read_lines(child.stderr.take().unwrap(), sender, Line::Stderr);
 
-    if is_verbose {
-        eprintln!("clippy command: {command:?}");
-        eprintln!("clippy output: {clippy_string}");
+    let mut clippy = clippy_writer(options, writer)?;
+    let mut stream = rewriter.message_stream(&options.subcommand);
+    let mut cargo_string = String::new();
+    // Lines of stderr that Clippit did not say while cargo was running
+    let mut unsaid_stderr = Vec::new();
+    let mut cargo_json = String::new();
+    let mut is_build_finished = false;
+    let mut crates = Vec::new();
+
+    // Stdout has the JSON messages, so Clippit can comment on them right away
+    for line in receiver {
+        match line {
+            Line::Stdout(line) => {
+                if line.starts_with('{') {
+                    for message in parse_messages(&line) {
+                        is_build_finished |= matches!(message, Message::BuildFinished(_));
+                        match &message {
+                            Message::CompilerMessage(compiler_message) => push_crate_diagnostic(
+                                &mut crates,
+                                package_name(&compiler_message.package_id),
+                                compiler_message.message.clone(),
+                            ),
+                            // The crates of the workspace are reported even without diagnostics
+                            Message::CompilerArtifact(artifact)
+                                if is_local_package(&artifact.package_id) =>
+                            {
+                                add_crate(&mut crates, package_name(&artifact.package_id));
+                            }
+                            _ => {}
+                        }
+                        write_parts(options, &mut clippy, stream.push(&message))?;
+                    }
+                    if options.verbose {
+                        cargo_json.push_str(&line);
+                        cargo_json.push('\n');
+                    }
+                } else {
+                    // The output of the tests for `cargo test`
+                    clippy.write_parts(&stream.push_line(&line))?;
+                }
+            }
+            Line::Stderr(line) => {
+                let parts = stream.push_stderr_line(&line);
+                clippy.write_parts(&parts)?;
+                cargo_string.push_str(&line);
+                cargo_string.push('\n');
+                if parts.is_empty() {
+                    unsaid_stderr.push(line);
+                }
+            }
+        }
     }
 
-    output(clippy_string, writer)?;
-    Ok(clippy_output.status.code().unwrap_or(0))
+    stdout_thread.join().unwrap()?;
+    stderr_thread.join().unwrap()?;
+    let status = child.wait()?;
+
+    if options.verbose {
+        eprintln!("cargo output: {cargo_string}");
+        eprintln!("cargo messages: {cargo_json}");
+    }
+
+    if !is_build_finished {
+        // Cargo failed before it could build anything, so the rest of its error is only on stderr
+        for line in &unsaid_stderr {
+            clippy.write_parts(&stream.push_line(line))?;
+        }
+    }
+    write_parts(options, &mut clippy, stream.finish())?;
+    finish(options, clippy)?;
+
+    if options.interactive {
+        ask_stdin(options, rewriter, &all_diagnostics(&crates), writer)?;
+    }
+    Ok((status.code().unwrap_or(0), crates))
+}
+
+/// Returns the arguments for cargo, which are the subcommand and the arguments that clippit does
+/// not know, with `--message-format` so that cargo writes JSON messages.
+fn cargo_args(options: &Options) -> Vec<String> {
+    let mut args = vec![options.subcommand.clone()];
+    args.extend(options.cargo_args.iter().cloned());
+
+    // Cargo's arguments come before "--"; the rest are passed to clippy or the tests
+    let cargo_args_end = args
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(args.len());
+    let color_arg = args[..cargo_args_end]
+        .iter()
+        .position(|arg| arg.starts_with("--color"));
+    if colors(options, &args, color_arg) {
+        // The rendered diagnostics in the JSON are colored too, and Clippit keeps the colors
+        args.insert(
+            cargo_args_end,
+            "--message-format=json-diagnostic-rendered-ansi".to_string(),
+        );
+        if color_arg.is_none() {
+            args.insert(cargo_args_end, "--color=always".to_string());
+        }
+    } else {
+        args.insert(cargo_args_end, "--message-format=json".to_string());
+    }
+    args
+}
+
+/// A line of cargo's output.
+enum Line {
+    Stdout(String),
+    Stderr(String),
+}
+
+/// Sends the lines of `reader` to `sender` from another thread, so that stdout and stderr can be
+/// read at the same time.
+fn read_lines<Reader>(
+    reader: Reader,
+    sender: Sender<Line>,
+    line: fn(String) -> Line,
+) -> JoinHandle<std::io::Result<()>>
+where
+    Reader: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        for text in BufReader::new(reader).lines() {
+            // The receiver is only gone if writing the output failed
+            if sender.send(line(text?)).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Phrases the output of cargo or rustc from `reader`, which can be human-readable or JSON. The
+/// diagnostics are only returned if they are needed for `--interactive` or the formats that say
+/// them on their own.
+fn filter<Reader, Writer>(
+    rewriter: &Rewriter,
+    options: &Options,
+    mut reader: Reader,
+    writer: &mut Writer,
+) -> Result<(i32, Vec<CrateDiagnostics>)>
+where
+    Reader: Read,
+    Writer: Write,
+{
+    let mut input = String::new();
+    reader.read_to_string(&mut input)?;
+    let mut clippy = clippy_writer(options, writer)?;
+    write_parts(options, &mut clippy, rewriter.replace_output_parts(&input))?;
+    finish(options, clippy)?;
+
+    if !options.interactive && matches!(options.format, Format::Text | Format::Html | Format::Svg) {
+        return Ok((0, Vec::new()));
+    }
+    let crates = parse_crates(&input);
+    if options.interactive {
+        ask_stdin(options, rewriter, &all_diagnostics(&crates), writer)?;
+    }
+    Ok((0, crates))
+}
+
+/// Returns the diagnostics of all crates.
+fn all_diagnostics(crates: &[CrateDiagnostics]) -> Vec<Diagnostic> {
+    crates
+        .iter()
+        .flat_map(|c| c.diagnostics.iter().cloned())
+        .collect()
+}
+
+/// Writes the rephrased output. In interactive mode, the diagnostics are left out, because Clippit
+/// asks about them one at a time afterwards, and the formats other than text, HTML and SVG say
+/// them on their own.
+fn write_parts<Writer>(
+    options: &Options,
+    clippy: &mut ClippyWriter<Writer>,
+    mut parts: Vec<Part>,
+) -> std::io::Result<()>
+where
+    Writer: Write,
+{
+    if options.interactive
+        || matches!(
+            options.format,
+            Format::Markdown | Format::Github | Format::Sarif | Format::Junit
+        )
+    {
+        parts.retain(|part| matches!(part, Part::Text(_)));
+    }
+    clippy.write_parts(&parts)
+}
+
+/// An answer to "Would you like help with that?"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Answer {
+    Yes,
+    No,
+    /// Don't show me this tip again
+    Dismiss,
+    /// Explains the lint or the error code, and asks again
+    Explain,
+    Quit,
+}
+
+impl Answer {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s.trim().to_lowercase().as_str() {
+            "y" | "yes" => Answer::Yes,
+            // Enter goes to the next diagnostic
+            "" | "n" | "no" | "next" => Answer::No,
+            "d" | "don't" | "dont" => Answer::Dismiss,
+            "e" | "explain" => Answer::Explain,
+            "q" | "quit" => Answer::Quit,
+            _ => return None,
+        })
+    }
+}
+
+/// Asks about `diagnostics` with the answers from stdin, and remembers the dismissed lints in the
+/// workspace's `DISMISSED_PATH`.
+fn ask_stdin<Writer>(
+    options: &Options,
+    rewriter: &Rewriter,
+    diagnostics: &[Diagnostic],
+    writer: &mut Writer,
+) -> Result<()>
+where
+    Writer: Write,
+{
+    let root = root(options);
+    let mut dismissed = Dismissed::load(&root.join(DISMISSED_PATH))?;
+    ask(
+        options,
+        rewriter,
+        diagnostics,
+        root,
+        &mut dismissed,
+        &mut std::io::stdin().lock(),
+        writer,
+    )
+}
+
+/// Says the warnings and errors one at a time, and asks whether to apply their machine-applicable
+/// suggestions or to not show tips about their lint again. The accepted suggestions are applied at
+/// the end to the files under `root`, and the dismissed lints are added to `dismissed`.
+fn ask<Input, Writer>(
+    options: &Options,
+    rewriter: &Rewriter,
+    diagnostics: &[Diagnostic],
+    root: &Path,
+    dismissed: &mut Dismissed,
+    input: &mut Input,
+    writer: &mut Writer,
+) -> Result<()>
+where
+    Input: BufRead,
+    Writer: Write,
+{
+    let catalog = rewriter.catalog();
+    let mut accepted: Vec<Edit> = Vec::new();
+    let mut dismissed_codes = Vec::new();
+
+    for diagnostic in diagnostics {
+        if diagnostic.is_summary() || !matches!(diagnostic.level, Level::Error | Level::Warning) {
+            continue;
+        }
+        let code = diagnostic.lint_name().or_else(|| diagnostic.error_code());
+        if code.is_some_and(|code| dismissed.contains(code)) {
+            continue;
+        }
+
+        let mut edits = fix::edits(diagnostic);
+        // A suggestion that overlaps an accepted one can only be applied after running cargo again
+        if edits
+            .iter()
+            .any(|edit| accepted.iter().any(|a| a.overlaps(edit)))
+        {
+            edits.clear();
+        }
+        // The files may have moved since cargo checked them
+        let preview = fix::preview(&edits, root).unwrap_or_default();
+        if preview.is_empty() {
+            edits.clear();
+        }
+
+        let mut clippy = clippy_writer(options, writer)?;
+        clippy.write_str(&rewriter.replace_diagnostics(std::slice::from_ref(diagnostic)))?;
+        let mut choices = Vec::new();
+        if edits.is_empty() {
+            choices.push("choice-next");
+        } else {
+            let question = catalog.text("would-you-like-help", &[]);
+            clippy.write_str(&format!("\n{question}\n"))?;
+            clippy.write_str(&preview)?;
+            choices.extend(["choice-yes", "choice-no"]);
+        }
+        if code.is_some() {
+            choices.extend(["choice-explain", "choice-dismiss"]);
+        }
+        choices.push("choice-quit");
+        let choices: Vec<String> = choices.iter().map(|key| catalog.text(key, &[])).collect();
+        clippy.write_str(&format!("\n{}\n", choices.join(" / ")))?;
+        clippy.finish()?;
+
+        let answer = loop {
+            write!(writer, "> ")?;
+            writer.flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                break Answer::Quit;
+            }
+            match (Answer::parse(&line), code) {
+                (Some(Answer::Explain), Some(code)) => {
+                    let explanation = match diagnostic.error_code() {
+                        Some(error_code) => rewriter.explain_error(error_code),
+                        None => explain_lint(code, catalog),
+                    };
+                    let explanation = explanation
+                        .unwrap_or_else(|| catalog.text("unknown-code", &[("code", code)]) + "\n");
+                    say(options, writer, &explanation)?;
+                }
+                (Some(Answer::Explain), None) | (None, _) => {}
+                (Some(answer), _) => break answer,
+            }
+        };
+        match (answer, code) {
+            (Answer::Yes, _) => accepted.extend(edits),
+            (Answer::Dismiss, Some(code)) => {
+                dismissed.insert(code);
+                dismissed_codes.push(code);
+            }
+            (Answer::Quit, _) => break,
+            _ => {}
+        }
+    }
+
+    let mut summary = String::new();
+    if !accepted.is_empty() {
+        let files = catalog.plural("files", fix::apply(&accepted, root)?, &[]);
+        let applied = catalog.plural("applied", accepted.len(), &[("files", &files)]);
+        writeln!(summary, "{applied}")?;
+    }
+    if !dismissed_codes.is_empty() {
+        dismissed.save()?;
+        let codes = dismissed_codes.join(", ");
+        writeln!(
+            summary,
+            "{}",
+            catalog.text("dismissed-now", &[("codes", &codes)])
+        )?;
+    }
+    if !summary.is_empty() {
+        say(options, writer, &summary)?;
+    }
+    Ok(())
+}
+
+/// Writes Clippit saying `s` in a speech bubble of its own.
+fn say<Writer>(options: &Options, writer: &mut Writer, s: &str) -> Result<()>
+where
+    Writer: Write,
+{
+    let mut clippy = clippy_writer(options, writer)?;
+    clippy.write_str(s)?;
+    clippy.finish()?;
+    Ok(())
+}
+
+/// Returns a different seed for each run.
+fn random_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| {
+            time.as_secs() ^ (u64::from(time.subsec_nanos()) << 32)
+        })
+        ^ u64::from(std::process::id())
+}
+
+/// Returns the phrases of the language from `--lang`, or else from the user's locale, in the tone
+/// of the personality from `--personality` or the rule files.
+fn catalog(options: &Options) -> Result<Catalog> {
+    let catalog = options
+        .lang
+        .as_deref()
+        .map_or_else(Catalog::from_env, Catalog::new);
+    // `--personality` overrides the rule files
+    let personality = match &options.personality {
+        Some(name) => Some(name.clone()),
+        None => configured_personality(options.config.as_deref())?,
+    };
+    let Some(name) = personality else {
+        return Ok(catalog);
+    };
+    let personality = load_personality(&name)?;
+    if !personality.speaks(catalog.language()) {
+        eprintln!(
+            "clippit: the {name} personality does not speak {}, so the classic phrases are used",
+            catalog.language()
+        );
+    }
+    Ok(catalog.with_personality(&personality))
+}
+
+/// Returns whether cargo's output is colored, from cargo's `--color` at `color_arg` or else from
+/// the theme and the output.
+fn colors(options: &Options, args: &[String], color_arg: Option<usize>) -> bool {
+    match color_arg {
+        Some(i) => args[i] == "--color=always" || args.get(i + 1).is_some_and(|a| a == "always"),
+        // The page and the image have colors, wherever they are written, and Markdown has none
+        None => {
+            options.theme != Theme::Plain
+                && match options.format {
+                    Format::Text => std::io::stderr().is_terminal(),
+                    Format::Html | Format::Svg => true,
+                    Format::Markdown | Format::Github | Format::Sarif | Format::Junit => false,
+                }
+        }
+    }
+}
+
+/// Returns the character from `--character`, or else from the rule files.
+fn character(options: &Options) -> Result<Character> {
+    // `--character` overrides the rule files
+    let character = match &options.character {
+        Some(name) => Some(name.clone()),
+        None => configured_character(options.config.as_deref())?,
+    };
+    match character {
+        Some(name) => load_character(&name),
+        None => Ok(Character::default()),
+    }
+}
+
+/// Renders Clippit's text output and the diagnostics in the format from `--format`.
+fn render(
+    options: &Options,
+    rewriter: &Rewriter,
+    text: &str,
+    crates: &[CrateDiagnostics],
+) -> Result<String> {
+    // The style of the speech bubble, so that the page and the image can color it
+    let bubble = || -> Result<Option<BubbleStyle>> {
+        Ok(if options.no_art {
+            None
+        } else {
+            Some(character(options)?.bubble)
+        })
+    };
+    Ok(match options.format {
+        Format::Text => text.to_string(),
+        Format::Html => render::html(text, bubble()?),
+        Format::Svg => render::svg(text, bubble()?),
+        Format::Markdown => render::markdown(text, rewriter, &all_diagnostics(crates)),
+        Format::Github => render::github(rewriter, &all_diagnostics(crates)),
+        Format::Sarif => render::sarif(rewriter, &all_diagnostics(crates)),
+        Format::Junit => render::junit(
+            rewriter,
+            crates,
+            options.junit_warnings == JunitWarnings::Skipped,
+        ),
+    })
+}
+
+/// Appends the Markdown to the file from `--step-summary`, or else from `GITHUB_STEP_SUMMARY`,
+/// which GitHub Actions shows on the summary page of the run.
+fn write_step_summary(
+    options: &Options,
+    rewriter: &Rewriter,
+    text: &str,
+    diagnostics: &[Diagnostic],
+) -> Result<()> {
+    let Some(path) = options
+        .step_summary
+        .clone()
+        .or_else(|| std::env::var_os("GITHUB_STEP_SUMMARY").map(PathBuf::from))
+    else {
+        return Ok(());
+    };
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("could not open {}", path.display()))?;
+    file.write_all(render::markdown(text, rewriter, diagnostics).as_bytes())?;
+    Ok(())
+}
+
+fn clippy_writer<'a, Writer>(
+    options: &Options,
+    writer: &'a mut Writer,
+) -> Result<ClippyWriter<'a, Writer>>
+where
+    Writer: Write,
+{
+    let mut clippy = if options.no_art {
+        ClippyWriter::plain(writer)
+    } else {
+        let character = character(options)?;
+        let width = options.width.unwrap_or_else(default_width);
+        let mut clippy = ClippyWriter::with_character(writer, width, &character);
+        if options.layout == Layout::Diagnostic {
+            clippy.split_bubbles(options.redraw_every);
+        }
+        clippy
+    };
+    clippy.set_colors(options.theme != Theme::Plain);
+    Ok(clippy)
+}
+
+/// Closes the speech bubble, unless Clippit is quiet and had nothing to say.
+fn finish<Writer>(options: &Options, clippy: ClippyWriter<Writer>) -> Result<()>
+where
+    Writer: Write,
+{
+    if !(options.quiet && clippy.is_empty()) {
+        clippy.finish()?;
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -42,15 +678,172 @@ mod tests {
 
     #[test]
     fn problematic_code() {
-        std::env::set_current_dir("problematic-code").unwrap();
-
         let mut output: Vec<u8> = Vec::new();
-        let status_code = run(vec!["-v".to_string()], &mut output).unwrap();
+        let options = parse_args(["-v".to_string()]).unwrap();
+        let rewriter = rewriter(&options).unwrap();
+        let (status_code, diagnostics) = run(
+            &options,
+            &rewriter,
+            Path::new("problematic-code"),
+            &mut output,
+        )
+        .unwrap();
 
         let output_str = std::str::from_utf8(&output).unwrap();
         println!("{output_str}");
 
         assert_ne!(status_code, 0);
         assert!(output_str.contains("problematic-code"));
+        assert!(!diagnostics.is_empty());
+    }
+
+    #[test]
+    fn cargo_args_are_forwarded() {
+        let options = parse_args(
+            [
+                "--theme", "plain", "clippy", "-q", "-v", "--config", "k=v", "--", "-D",
+            ]
+            .map(String::from),
+        )
+        .unwrap();
+        assert_eq!(
+            cargo_args(&options),
+            [
+                "clippy",
+                "-q",
+                "-v",
+                "--config",
+                "k=v",
+                "--message-format=json",
+                "--",
+                "-D"
+            ]
+        );
+    }
+
+    #[test]
+    fn filter_human_and_json() {
+        let human =
+            "warning: unused variable: `pi`\n  = note: `#[warn(unused_variables)]` on by default\n";
+        let json = r#"{"reason":"compiler-message","package_id":"path+file:///playground#0.0.1","target":{"kind":["bin"],"name":"playground"},"message":{"rendered":null,"children":[],"level":"warning","message":"unused variable: `pi`","spans":[],"code":{"code":"unused_variables","explanation":null}}}
+{"reason":"build-finished","success":true}
+"#;
+
+        for input in [human, json] {
+            let mut output: Vec<u8> = Vec::new();
+            let (status_code, _) = filter(
+                &Rewriter::default(),
+                &Options::default(),
+                input.as_bytes(),
+                &mut output,
+            )
+            .unwrap();
+
+            let output_str = std::str::from_utf8(&output).unwrap();
+            assert_eq!(status_code, 0);
+            assert!(output_str.contains("Hmmm... unused variable: `pi`."));
+        }
+    }
+
+    #[test]
+    fn filter_quiet_and_no_art() {
+        let finished = "    Checking playground v0.0.1 (/playground)\n    Finished dev [unoptimized + debuginfo] target(s) in 0.20s\n";
+        let warning = "warning: unused variable: `pi`\n";
+        let filter_str = |options: &Options, input: &str| {
+            let mut rewriter = Rewriter::default();
+            rewriter.set_quiet(options.quiet);
+            let mut output: Vec<u8> = Vec::new();
+            filter(&rewriter, options, input.as_bytes(), &mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let quiet = parse_args(["--quiet".to_string()]).unwrap();
+        assert_eq!(filter_str(&quiet, finished), "");
+        assert!(filter_str(&quiet, warning).contains("| Hmmm... unused variable: `pi`."));
+
+        let no_art = parse_args(["--no-art".to_string()]).unwrap();
+        assert_eq!(
+            filter_str(&no_art, warning),
+            "Hmmm... unused variable: `pi`.\n"
+        );
+
+        // In Markdown, the diagnostics are returned for their own sections instead
+        let markdown = parse_args(["--no-art", "--format", "md"].map(String::from)).unwrap();
+        let mut output: Vec<u8> = Vec::new();
+        let (_, diagnostics) = filter(
+            &Rewriter::default(),
+            &markdown,
+            warning.as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        assert!(output.is_empty());
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn answers() {
+        assert_eq!(Answer::parse("Y\n"), Some(Answer::Yes));
+        assert_eq!(Answer::parse("\n"), Some(Answer::No));
+        assert_eq!(Answer::parse("don't"), Some(Answer::Dismiss));
+        assert_eq!(Answer::parse("q"), Some(Answer::Quit));
+        assert_eq!(Answer::parse("explain"), Some(Answer::Explain));
+        assert_eq!(Answer::parse("maybe"), None);
+    }
+
+    #[test]
+    fn ask_interactive() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let source = dir.join("main.rs");
+        std::fs::write(
+            &source,
+            "fn main() {\n    let a = ((0));\n    let b = ((1));\n}\n",
+        )
+        .unwrap();
+
+        let diagnostic = |line: usize, byte_start: usize| -> Diagnostic {
+            let span = format!(
+                r#"{{"file_name":{:?},"byte_start":{byte_start},"byte_end":{},"line_start":{line},"line_end":{line},"column_start":13,"column_end":18,"is_primary":true"#,
+                source.display().to_string(),
+                byte_start + 5,
+            );
+            serde_json::from_str(&format!(
+                r#"{{"message":"consider removing unnecessary double parentheses","code":{{"code":"clippy::double_parens","explanation":null}},"level":"warning","spans":[{span}}}],"children":[{{"message":"remove them","code":null,"level":"help","spans":[{span},"suggested_replacement":"{}","suggestion_applicability":"MachineApplicable"}}],"children":[]}}]}}"#,
+                line - 2,
+            ))
+            .unwrap()
+        };
+        let diagnostics = [diagnostic(2, 24), diagnostic(3, 43)];
+
+        let mut dismissed = Dismissed::load(&dir.join("dismissed")).unwrap();
+        let mut output: Vec<u8> = Vec::new();
+        ask(
+            &parse_args(["--no-art".to_string()]).unwrap(),
+            &Rewriter::default(),
+            &diagnostics,
+            Path::new("."),
+            &mut dismissed,
+            &mut "maybe\ny\ne\nd\n".as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        let fixed = std::fs::read_to_string(&source).unwrap();
+        let saved = Dismissed::load(&dir.join("dismissed")).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Would you like help with that?"));
+        assert!(output.contains("   2 -     let a = ((0));\n   2 +     let a = 0;\n"));
+        assert!(
+            output.contains("[y]es / [n]o / [e]xplain / [d]on't show me this tip again / [q]uit")
+        );
+        assert!(output.contains("Did you know? `clippy::double_parens` checks for"));
+        assert!(output.contains("I applied 1 suggestion to 1 file."));
+        assert!(output.contains("I won't show you tips about clippy::double_parens again."));
+        assert_eq!(
+            fixed,
+            "fn main() {\n    let a = 0;\n    let b = ((1));\n}\n"
+        );
+        assert!(saved.contains("clippy::double_parens"));
     }
 }
@@ -0,0 +1,184 @@
+//! The JSON records that cargo writes with `--message-format=json`, e.g. for `cargo clippy`.
+
+use crate::diagnostic::Diagnostic;
+use serde::Deserialize;
+
+/// A record from cargo's `--message-format=json` output.
+///
+/// Records that clippit does not use are deserialized as `Message::Unknown`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub enum Message {
+    CompilerMessage(CompilerMessage),
+    CompilerArtifact(Artifact),
+    BuildFinished(BuildFinished),
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CompilerMessage {
+    pub package_id: String,
+    pub target: Target,
+    pub message: Diagnostic,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Artifact {
+    pub package_id: String,
+    pub target: Target,
+    /// `true` if the artifact was up to date and nothing was compiled.
+    #[serde(default)]
+    pub fresh: bool,
+    /// The files that were written, e.g. `target/debug/deps/libplayground.rmeta`.
+    #[serde(default)]
+    pub filenames: Vec<String>,
+}
+
+impl Artifact {
+    /// Returns `true` for the documentation written by `cargo doc`, as opposed to the metadata
+    /// of the dependencies that rustdoc needs.
+    pub fn is_documentation(&self) -> bool {
+        self.filenames.iter().any(|file| file.ends_with(".html"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BuildFinished {
+    pub success: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct Target {
+    pub name: String,
+    pub kind: Vec<String>,
+}
+
+impl Target {
+    /// Describes the target the way cargo does in its summary lines, e.g. `bin "playground"`.
+    pub fn description(&self) -> String {
+        match self.kind.first().map(String::as_str) {
+            Some("lib" | "rlib" | "dylib" | "cdylib" | "staticlib" | "proc-macro") => {
+                "lib".to_string()
+            }
+            Some("custom-build") => "build script".to_string(),
+            Some(kind) => format!("{kind} \"{}\"", self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Parses cargo's JSON output. Lines that are not JSON records are skipped.
+pub fn parse_messages(input: &str) -> Vec<Message> {
+    input
+        .lines()
+        .filter(|line| line.starts_with('{'))
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Returns the name and version of a package, followed by its path if it is a local package, e.g.
+/// `playground v0.0.1 (/playground)`.
+pub fn package_description(package_id: &str) -> String {
+    let (name, version, path) = parse_package_id(package_id);
+    match path {
+        Some(path) => format!("{name} v{version} ({path})"),
+        None => format!("{name} v{version}"),
+    }
+}
+
+/// Returns the name of a package from its package ID.
+pub fn package_name(package_id: &str) -> &str {
+    parse_package_id(package_id).0
+}
+
+/// Returns `true` for a package in a local path, such as a workspace member, as opposed to a
+/// dependency from a registry or git.
+pub fn is_local_package(package_id: &str) -> bool {
+    parse_package_id(package_id).2.is_some()
+}
+
+/// Splits a package ID into its name, version and local path.
+///
+/// Both the old (`name version (source)`) and the new (`source#name@version`) package ID formats
+/// are supported.
+fn parse_package_id(package_id: &str) -> (&str, &str, Option<&str>) {
+    if let Some((source, fragment)) = package_id.split_once('#') {
+        let (name, version) = match fragment.split_once('@') {
+            Some((name, version)) => (name, version),
+            // The name is omitted if it is the same as the last path segment
+            None => (
+                source
+                    .trim_end_matches('/')
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(""),
+                fragment,
+            ),
+        };
+        (name, version, source.strip_prefix("path+file://"))
+    } else {
+        let mut parts = package_id.splitn(3, ' ');
+        let name = parts.next().unwrap_or("");
+        let version = parts.next().unwrap_or("");
+        let path = parts.next().and_then(|source| {
+            source
+                .strip_prefix("(path+file://")
+                .and_then(|path| path.strip_suffix(')'))
+        });
+        (name, version, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(
+        "path+file:///root/crate/problematic-code#0.1.0",
+        "problematic-code v0.1.0 (/root/crate/problematic-code)"
+    )]
+    #[case("path+file:///playground#play@0.0.1", "play v0.0.1 (/playground)")]
+    #[case(
+        "registry+https://github.com/rust-lang/crates.io-index#serde@1.0.188",
+        "serde v1.0.188"
+    )]
+    #[case(
+        "playground 0.0.1 (path+file:///playground)",
+        "playground v0.0.1 (/playground)"
+    )]
+    #[case(
+        "serde 1.0.188 (registry+https://github.com/rust-lang/crates.io-index)",
+        "serde v1.0.188"
+    )]
+    fn test_package_description(#[case] package_id: &str, #[case] expected: &str) {
+        assert_eq!(package_description(package_id), expected);
+    }
+
+    #[test]
+    fn test_parse_messages() {
+        let input = r#"{"reason":"compiler-artifact","package_id":"path+file:///playground#0.0.1","target":{"kind":["bin"],"name":"playground"},"fresh":false}
+    Checking playground v0.0.1 (/playground)
+{"reason":"build-script-executed","package_id":"path+file:///playground#0.0.1"}
+{"reason":"build-finished","success":true}
+"#;
+        assert_eq!(
+            parse_messages(input),
+            vec![
+                Message::CompilerArtifact(Artifact {
+                    package_id: "path+file:///playground#0.0.1".to_string(),
+                    target: Target {
+                        name: "playground".to_string(),
+                        kind: vec!["bin".to_string()],
+                    },
+                    fresh: false,
+                    filenames: Vec::new(),
+                }),
+                Message::Unknown,
+                Message::BuildFinished(BuildFinished { success: true }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,555 @@
+//! Clippit's command line options.
+//!
+//! Clippit's own options come before the subcommand. Everything from the subcommand or from the
+//! first argument that clippit does not know is passed to cargo unchanged, e.g.
+//! `clippit --width 80 --all-targets -- -D warnings` or `clippit build -q --config build.jobs=4`.
+
+use crate::character::builtin_characters;
+use crate::locale::LANGUAGES;
+use crate::personality::builtin_personalities;
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub const HELP: &str = "\
+Makes cargo clippy sound like Office 2003's Clippit assistant.
+
+Usage: clippit [OPTIONS] [SUBCOMMAND] [CARGO OPTIONS] [-- ARGS]
+       clippit [OPTIONS] <FILE | ->
+
+Rephrases the output of `cargo SUBCOMMAND`, or cargo or rustc output that was saved to FILE or
+piped to stdin with `-`. SUBCOMMAND is one of clippy, check, build, test, doc [default: clippy].
+Clippit's options come first. All arguments from SUBCOMMAND or from the first option that clippit
+does not know are passed to cargo unchanged, e.g. `clippit clippy -q --config KEY=VALUE`.
+
+Options:
+      --width <WIDTH>          Width of the speech bubble [default: terminal width, at most 120]
+      --character <NAME>       Assistant that says the output: clippit, rover, merlin, links, f1,
+                               office, or the path of a character file [default: clippit]
+      --personality <NAME>     Tone of the phrases: classic, encouraging, sarcastic, corporate, terse,
+                               silent, or the path of a personality file [default: classic]
+      --theme <THEME>          Colors of the output: default (cargo's colors on a terminal), plain
+                               [default: default]
+      --format <FORMAT>        Output format: text (on stderr), html (a page on stdout), svg (an
+                               image on stdout), markdown (for pull request comments, on stdout),
+                               github (GitHub Actions annotations on stdout), sarif (a SARIF log
+                               on stdout), junit (a JUnit XML report on stdout) [default: text]
+      --step-summary <PATH>    With --format github, append the Markdown to PATH [default:
+                               GITHUB_STEP_SUMMARY]
+      --junit-warnings <AS>    With --format junit, report warnings as failure or skipped
+                               [default: failure]
+      --layout <LAYOUT>        Speech bubbles: single, diagnostic (one per diagnostic)
+                               [default: single]
+      --redraw-every <N>       With --layout diagnostic, draw the assistant above every Nth bubble,
+                               or only above the first one if N is 0 [default: 0]
+      --seed <N>               Seed for picking the phrasing of rules with alternatives, to get the
+                               same output again [default: random with --format text, otherwise
+                               none, so that reports do not change between runs]
+      --config <PATH>          Rule file to use instead of ./clippit.toml
+      --lang <LANG>            Language that the assistant speaks: en, ja, de [default: from
+                               LC_ALL, LC_MESSAGES or LANG]
+  -i, --interactive            Ask about each diagnostic, and apply the suggested fixes that you
+                               accept
+      --explain <LINT | CODE>  Explain a clippy lint such as double_parens, or an error code such as
+                               E0597, instead of running cargo
+      --explain-lints          Explain each clippy lint below its first warning
+      --explain-errors         Explain each error code below its first error with rustc --explain
+      --dismissed <COMMAND>    Lints that you don't want tips about: list, reset, export (as a
+                               [lints] table for Cargo.toml)
+      --no-art                 Only write the rephrased text, without the assistant and the bubble
+  -q, --quiet                  Only say something about warnings and errors
+  -v, --verbose                Print the cargo command and its output
+  -h, --help                   Print this help
+  -V, --version                Print the version
+";
+
+/// Cargo subcommands that clippit can run. The first one is the default.
+pub const SUBCOMMANDS: &[&str] = &["clippy", "check", "build", "test", "doc"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    /// Width of the speech bubble, or `None` to fit the terminal.
+    pub width: Option<u16>,
+    /// Name of a built-in character or the path of a character file, or `None` for the character
+    /// from the rule files.
+    pub character: Option<String>,
+    /// Name of a built-in personality or the path of a personality file, or `None` for the
+    /// personality from the rule files.
+    pub personality: Option<String>,
+    pub theme: Theme,
+    pub format: Format,
+    pub layout: Layout,
+    /// Draws the character above every `redraw_every`th speech bubble with `Layout::Diagnostic`,
+    /// or only above the first one if 0.
+    pub redraw_every: usize,
+    /// Seed for picking the phrasing of rules with alternatives, or `None` for a random one with
+    /// `Format::Text` and none with the other formats.
+    pub seed: Option<u64>,
+    /// File that the Markdown is appended to with `Format::Github`, or `None` for the file from
+    /// `GITHUB_STEP_SUMMARY`.
+    pub step_summary: Option<PathBuf>,
+    pub junit_warnings: JunitWarnings,
+    /// Rule file that replaces `./clippit.toml`.
+    pub config: Option<PathBuf>,
+    /// Language code from `locale::LANGUAGES`, or `None` for the language of the user's locale.
+    pub lang: Option<String>,
+    /// Asks about the diagnostics one at a time after cargo finished.
+    pub interactive: bool,
+    /// Lint or error code to explain instead of running cargo.
+    pub explain: Option<String>,
+    pub explain_lints: bool,
+    pub explain_errors: bool,
+    /// Shows or changes the dismissed lints instead of running cargo.
+    pub dismissed: Option<DismissedCommand>,
+    pub no_art: bool,
+    pub quiet: bool,
+    pub verbose: bool,
+    pub help: bool,
+    pub version: bool,
+    /// Cargo subcommand to run, one of `SUBCOMMANDS`.
+    pub subcommand: String,
+    /// `-` for stdin or the path of saved output to rephrase instead of running cargo.
+    pub input: Option<String>,
+    /// Arguments for cargo, including `--` and the arguments for clippy or the tests after it.
+    pub cargo_args: Vec<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            width: None,
+            character: None,
+            personality: None,
+            theme: Theme::default(),
+            format: Format::default(),
+            layout: Layout::default(),
+            redraw_every: 0,
+            seed: None,
+            step_summary: None,
+            junit_warnings: JunitWarnings::default(),
+            config: None,
+            lang: None,
+            interactive: false,
+            explain: None,
+            explain_lints: false,
+            explain_errors: false,
+            dismissed: None,
+            no_art: false,
+            quiet: false,
+            verbose: false,
+            help: false,
+            version: false,
+            subcommand: SUBCOMMANDS[0].to_string(),
+            input: None,
+            cargo_args: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    #[default]
+    Default,
+    /// Never uses colors.
+    Plain,
+}
+
+impl FromStr for Theme {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "default" => Theme::Default,
+            "plain" => Theme::Plain,
+            _ => bail!("unknown theme: {s}"),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// Clippit saying the output in a speech bubble.
+    #[default]
+    Text,
+    /// The same text in a self-contained HTML page, with links.
+    Html,
+    /// The same text in an SVG image.
+    Svg,
+    /// A Markdown document for pull request comments, with a collapsible section per diagnostic.
+    Markdown,
+    /// GitHub Actions workflow commands that annotate the diagnostics on the diff.
+    Github,
+    /// A SARIF 2.1.0 log of the diagnostics, e.g. for a code scanning dashboard.
+    Sarif,
+    /// A JUnit XML report with a test suite per crate and a test case per diagnostic.
+    Junit,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "text" => Format::Text,
+            "html" => Format::Html,
+            "svg" => Format::Svg,
+            "markdown" | "md" => Format::Markdown,
+            "github" => Format::Github,
+            "sarif" => Format::Sarif,
+            "junit" => Format::Junit,
+            _ => bail!("unknown format: {s}"),
+        })
+    }
+}
+
+/// How warnings are reported in `Format::Junit`. Errors are always failures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JunitWarnings {
+    #[default]
+    Failure,
+    Skipped,
+}
+
+impl FromStr for JunitWarnings {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "failure" => JunitWarnings::Failure,
+            "skipped" => JunitWarnings::Skipped,
+            _ => bail!("unknown result for warnings: {s}"),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// All output in one speech bubble.
+    #[default]
+    Single,
+    /// Each diagnostic in its own speech bubble.
+    Diagnostic,
+}
+
+impl FromStr for Layout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "single" => Layout::Single,
+            "diagnostic" => Layout::Diagnostic,
+            _ => bail!("unknown layout: {s}"),
+        })
+    }
+}
+
+/// What to do with the lints that the user dismissed in `--interactive` mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DismissedCommand {
+    List,
+    Reset,
+    /// Prints a `[lints]` table for `Cargo.toml`.
+    Export,
+}
+
+impl FromStr for DismissedCommand {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "list" => DismissedCommand::List,
+            "reset" => DismissedCommand::Reset,
+            "export" => DismissedCommand::Export,
+            _ => bail!("unknown command for --dismissed: {s}"),
+        })
+    }
+}
+
+/// Separates clippit's options from the arguments for cargo. `args` does not include the program
+/// name.
+pub fn parse_args<I>(args: I) -> Result<Options>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        // "--width=80" or "--width 80"
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut next_value = || value(name, inline_value.clone(), &mut args);
+
+        match name {
+            "--width" => {
+                let width = next_value()?;
+                options.width = Some(
+                    width
+                        .parse()
+                        .with_context(|| format!("invalid width: {width}"))?,
+                );
+            }
+            "--character" => {
+                let character = next_value()?;
+                if !builtin_characters().iter().any(|c| c.name == character)
+                    && !Path::new(&character).is_file()
+                {
+                    bail!("unknown character: {character}");
+                }
+                options.character = Some(character);
+            }
+            "--personality" => {
+                let personality = next_value()?;
+                if !builtin_personalities()
+                    .iter()
+                    .any(|p| p.name == personality)
+                    && !Path::new(&personality).is_file()
+                {
+                    bail!("unknown personality: {personality}");
+                }
+                options.personality = Some(personality);
+            }
+            "--theme" => options.theme = next_value()?.parse()?,
+            "--format" => options.format = next_value()?.parse()?,
+            "--layout" => options.layout = next_value()?.parse()?,
+            "--redraw-every" => {
+                let n = next_value()?;
+                options.redraw_every = n
+                    .parse()
+                    .with_context(|| format!("invalid number of bubbles: {n}"))?;
+            }
+            "--seed" => {
+                let seed = next_value()?;
+                options.seed = Some(
+                    seed.parse()
+                        .with_context(|| format!("invalid seed: {seed}"))?,
+                );
+            }
+            "--step-summary" => options.step_summary = Some(PathBuf::from(next_value()?)),
+            "--junit-warnings" => options.junit_warnings = next_value()?.parse()?,
+            "--config" => options.config = Some(PathBuf::from(next_value()?)),
+            "--lang" => {
+                let lang = next_value()?;
+                if !LANGUAGES.contains(&lang.as_str()) {
+                    bail!("unknown language: {lang}");
+                }
+                options.lang = Some(lang);
+            }
+            "-i" | "--interactive" => options.interactive = true,
+            "--explain" => options.explain = Some(next_value()?),
+            "--explain-lints" => options.explain_lints = true,
+            "--explain-errors" => options.explain_errors = true,
+            "--dismissed" => options.dismissed = Some(next_value()?.parse()?),
+            "--no-art" => options.no_art = true,
+            "-q" | "--quiet" => options.quiet = true,
+            "-v" | "--verbose" => options.verbose = true,
+            "-h" | "--help" => options.help = true,
+            "-V" | "--version" => options.version = true,
+            "--" => {
+                options.cargo_args.push(arg);
+                options.cargo_args.extend(args.by_ref());
+            }
+            // A subcommand takes precedence over a saved log with the same name. The arguments
+            // after it are cargo's, even if clippit has an option with the same name.
+            _ if options.input.is_none() && SUBCOMMANDS.contains(&name) => {
+                options.subcommand = arg;
+                options.cargo_args.extend(args.by_ref());
+            }
+            _ if options.input.is_none() && (arg == "-" || !arg.starts_with('-')) => {
+                options.input = Some(arg);
+            }
+            // Clippit's options end at the first argument that it does not know
+            _ => {
+                options.cargo_args.push(arg);
+                options.cargo_args.extend(args.by_ref());
+            }
+        }
+    }
+
+    // Arguments after "--" are for clippy or the tests
+    let cargo_args_end = options
+        .cargo_args
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(options.cargo_args.len());
+    if options.cargo_args[..cargo_args_end]
+        .iter()
+        .any(|arg| arg.starts_with("--message-format"))
+    {
+        bail!(
+            "clippit reads cargo's JSON messages, so --message-format cannot be passed to cargo; \
+             save cargo's output to a file and run clippit <FILE> instead"
+        );
+    }
+    if options.interactive && options.input.as_deref() == Some("-") {
+        bail!("--interactive reads the answers from stdin, so the output cannot be read from it");
+    }
+    if options.interactive && options.format != Format::Text {
+        bail!("--interactive asks in the terminal, so it only works with --format text");
+    }
+    if options.step_summary.is_some() && options.format != Format::Github {
+        bail!("--step-summary only works with --format github");
+    }
+    Ok(options)
+}
+
+/// Returns the value of the option `name`, which is either after `=` or the next argument.
+fn value<I>(name: &str, inline_value: Option<String>, args: &mut I) -> Result<String>
+where
+    I: Iterator<Item = String>,
+{
+    match inline_value {
+        Some(value) => Ok(value),
+        None => args
+            .next()
+            .with_context(|| format!("{name} requires a value")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[rstest]
+    #[case("", None, "clippy", None, &[])]
+    #[case("--width 80 -q", Some(80), "clippy", None, &[])]
+    #[case("--width=80 --all-targets", Some(80), "clippy", None, &["--all-targets"])]
+    #[case(
+        "--no-art --features foo -- -D warnings -v",
+        None,
+        "clippy",
+        None,
+        &["--features", "foo", "--", "-D", "warnings", "-v"]
+    )]
+    #[case("-", None, "clippy", Some("-"), &[])]
+    #[case("--width 60 build.log", Some(60), "clippy", Some("build.log"), &[])]
+    #[case("build --release", None, "build", None, &["--release"])]
+    #[case("-q test -- --nocapture", None, "test", None, &["--", "--nocapture"])]
+    #[case("check build", None, "check", None, &["build"])]
+    #[case("test my_filter", None, "test", None, &["my_filter"])]
+    #[case("build -p foo", None, "build", None, &["-p", "foo"])]
+    #[case("--all-targets --width 80", None, "clippy", None, &["--all-targets", "--width", "80"])]
+    #[case(
+        "--width 80 clippy -q -v --config build.jobs=4",
+        Some(80),
+        "clippy",
+        None,
+        &["-q", "-v", "--config", "build.jobs=4"]
+    )]
+    #[case("check --explain E0597", None, "check", None, &["--explain", "E0597"])]
+    #[case(
+        "test -- --message-format json",
+        None,
+        "test",
+        None,
+        &["--", "--message-format", "json"]
+    )]
+    fn test_parse_args(
+        #[case] input: &str,
+        #[case] width: Option<u16>,
+        #[case] subcommand: &str,
+        #[case] input_path: Option<&str>,
+        #[case] cargo_args: &[&str],
+    ) {
+        let options = parse_args(args(input)).unwrap();
+        assert_eq!(options.width, width);
+        assert_eq!(options.subcommand, subcommand);
+        assert_eq!(options.input.as_deref(), input_path);
+        assert_eq!(options.cargo_args, cargo_args);
+    }
+
+    #[test]
+    fn test_parse_args_flags() {
+        let options = parse_args(args(
+            "-v --quiet -i --no-art --theme plain --format text --config rules.toml --help -V \
+             --character rover --personality terse --explain double_parens --explain-lints --explain-errors --dismissed export --layout diagnostic --redraw-every 3 --lang ja --seed 42",
+        ))
+        .unwrap();
+        assert_eq!(
+            options,
+            Options {
+                character: Some("rover".to_string()),
+                personality: Some("terse".to_string()),
+                theme: Theme::Plain,
+                layout: Layout::Diagnostic,
+                redraw_every: 3,
+                config: Some(PathBuf::from("rules.toml")),
+                seed: Some(42),
+                lang: Some("ja".to_string()),
+                interactive: true,
+                explain: Some("double_parens".to_string()),
+                explain_lints: true,
+                explain_errors: true,
+                dismissed: Some(DismissedCommand::Export),
+                no_art: true,
+                quiet: true,
+                verbose: true,
+                help: true,
+                version: true,
+                ..Options::default()
+            }
+        );
+    }
+
+    #[rstest]
+    #[case("text", Format::Text)]
+    #[case("html", Format::Html)]
+    #[case("svg", Format::Svg)]
+    #[case("markdown", Format::Markdown)]
+    #[case("md", Format::Markdown)]
+    #[case("github", Format::Github)]
+    #[case("sarif", Format::Sarif)]
+    #[case("junit", Format::Junit)]
+    fn test_parse_format(#[case] format: &str, #[case] expected: Format) {
+        let options = parse_args(args(&format!("--format {format}"))).unwrap();
+        assert_eq!(options.format, expected);
+    }
+
+    #[test]
+    fn test_parse_args_cargo_flags() {
+        // After the subcommand, cargo's flags are not clippit's
+        let options = parse_args(args("-v clippy -q -v --config build.jobs=4")).unwrap();
+        assert!(options.verbose);
+        assert!(!options.quiet);
+        assert_eq!(options.config, None);
+        assert_eq!(options.cargo_args, ["-q", "-v", "--config", "build.jobs=4"]);
+    }
+
+    #[test]
+    fn test_parse_format_options() {
+        let options = parse_args(args("--format github --step-summary summary.md")).unwrap();
+        assert_eq!(options.step_summary, Some(PathBuf::from("summary.md")));
+
+        let options = parse_args(args("--format junit --junit-warnings skipped")).unwrap();
+        assert_eq!(options.junit_warnings, JunitWarnings::Skipped);
+    }
+
+    #[rstest]
+    #[case("--width")]
+    #[case("--width wide")]
+    #[case("--character bob")]
+    #[case("--personality grumpy")]
+    #[case("--theme neon")]
+    #[case("--format pdf")]
+    #[case("--format html -i")]
+    #[case("--step-summary summary.md")]
+    #[case("--junit-warnings ignored")]
+    #[case("--message-format short")]
+    #[case("build --message-format=human")]
+    #[case("--layout grid")]
+    #[case("--interactive -")]
+    #[case("--dismissed forget")]
+    #[case("--lang fr")]
+    #[case("--seed -1")]
+    #[case("--redraw-every -1")]
+    fn test_parse_args_invalid(#[case] input: &str) {
+        assert!(parse_args(args(input)).is_err());
+    }
+}
@@ -0,0 +1,203 @@
+//! Tones that Clippit can say the output in, from encouraging to sarcastic.
+//!
+//! The built-in personalities are in `src/personalities.toml`, which also describes the format.
+//! Other personalities can be loaded from a file with the same format.
+
+use crate::locale::Message;
+use crate::rules::configured_setting;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+const BUILTIN_PERSONALITIES: &str = include_str!("personalities.toml");
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Personality {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Languages from `locale::LANGUAGES` that the phrases are in.
+    #[serde(default = "english")]
+    pub languages: Vec<String>,
+    /// Replacements of the built-in rules by their `id`.
+    #[serde(default)]
+    pub rules: HashMap<String, String>,
+    /// Phrases from `src/locales` by their key.
+    #[serde(default)]
+    pub messages: HashMap<String, Message>,
+}
+
+impl Personality {
+    /// Returns `true` if the phrases are in `language`, so that they can replace the ones of a
+    /// catalog in that language.
+    pub fn speaks(&self, language: &str) -> bool {
+        self.languages.iter().any(|l| l == language)
+    }
+}
+
+fn english() -> Vec<String> {
+    vec!["en".to_string()]
+}
+
+#[derive(Deserialize)]
+struct PersonalityFile {
+    personalities: Vec<Personality>,
+}
+
+/// Returns the built-in personalities. The first one is the classic Clippit.
+pub fn builtin_personalities() -> Vec<Personality> {
+    toml::from_str::<PersonalityFile>(BUILTIN_PERSONALITIES)
+        .unwrap()
+        .personalities
+}
+
+/// Returns the built-in personality called `name`, or else the personality in the file at `name`.
+pub fn load_personality(name: &str) -> Result<Personality> {
+    if let Some(personality) = builtin_personalities().into_iter().find(|p| p.name == name) {
+        return Ok(personality);
+    }
+    let s = std::fs::read_to_string(name)
+        .with_context(|| format!("unknown personality: {name} is neither built in nor a file"))?;
+    toml::from_str(&s).with_context(|| format!("invalid personality file {name}"))
+}
+
+/// Returns the personality set with `personality = "..."` in the rule files from
+/// `rule_file_paths()`. A later file overrides an earlier one.
+pub fn configured_personality(config: Option<&Path>) -> Result<Option<String>> {
+    configured_setting(config, "personality")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::{Catalog, LANGUAGES};
+    use crate::rules::builtin_rules;
+    use crate::Rewriter;
+    use regex::Regex;
+
+    #[test]
+    fn test_builtin_personalities() {
+        let personalities = builtin_personalities();
+        let names: Vec<&str> = personalities.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "classic",
+                "encouraging",
+                "sarcastic",
+                "corporate",
+                "terse",
+                "silent"
+            ]
+        );
+
+        // Each phrase replaces a built-in one, and only uses the captures of its pattern
+        let english = Catalog::default();
+        let rules = builtin_rules();
+        let capture = Regex::new(r"\$\{?(\d+)").unwrap();
+        for personality in &personalities {
+            for language in &personality.languages {
+                assert!(
+                    LANGUAGES.contains(&language.as_str()),
+                    "{}",
+                    personality.name
+                );
+            }
+            for key in personality.messages.keys() {
+                assert_ne!(english.text(key, &[]), *key, "{}: {key}", personality.name);
+            }
+            for (id, replacement) in &personality.rules {
+                let rule = rules.iter().find(|rule| &rule.id == id);
+                let rule =
+                    rule.unwrap_or_else(|| panic!("{}: unknown rule {id}", personality.name));
+                let groups = Regex::new(&rule.pattern).unwrap().captures_len();
+                for c in capture.captures_iter(&replacement.replace("$$", "")) {
+                    assert!(
+                        c[1].parse::<usize>().unwrap() < groups,
+                        "{}: {id}",
+                        personality.name
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_personalities() {
+        let input = "    Checking playground v0.0.1 (/playground)
+warning: unused variable: `a`
+  = help: if this is intentional, prefix it with an underscore: `_a`
+    Finished dev [unoptimized + debuginfo] target(s) in 0.40s
+";
+        let say = |name: &str, input: &str| {
+            let mut rewriter = Rewriter::default();
+            let personality = load_personality(name).unwrap();
+            rewriter.set_catalog(Catalog::default().with_personality(&personality));
+            rewriter.replace_words(input)
+        };
+
+        assert_eq!(
+            say("classic", input),
+            Rewriter::default().replace_words(input)
+        );
+        assert_eq!(
+            say("terse", input),
+            "Checking playground v0.0.1 (/playground)
+Warning: unused variable: `a`.
+  Hint: if this is intentional, prefix it with an underscore: `_a`.
+Done dev [unoptimized + debuginfo] target(s) in 0.40s.
+"
+        );
+        assert_eq!(
+            say("encouraging", "    Finished dev"),
+            "All done compiling dev. Nice work!\nAmazing, no warnings! You're doing great!\n"
+        );
+        // The silent personality keeps cargo's lines and does not add any
+        assert_eq!(say("silent", input), input);
+        assert_eq!(say("silent", "    Finished dev"), "    Finished dev\n");
+        assert!(load_personality("grumpy").is_err());
+    }
+
+    #[test]
+    fn test_personality_languages() {
+        let input = "warning: unused variable: `a`\n";
+        let say = |language: &str, name: &str| {
+            let mut rewriter = Rewriter::default();
+            let personality = load_personality(name).unwrap();
+            rewriter.set_catalog(Catalog::new(language).with_personality(&personality));
+            rewriter.replace_words(input)
+        };
+
+        // The English personality does not replace the Japanese phrases
+        assert_eq!(say("ja", "sarcastic"), say("ja", "classic"));
+        assert_eq!(say("ja", "sarcastic"), "うーん... unused variable: `a`。\n");
+        assert_eq!(say("en", "sarcastic"), "Really? unused variable: `a`.\n");
+        // Silent has no words, so it speaks every language
+        assert_eq!(say("de", "silent"), input);
+    }
+
+    #[test]
+    fn test_load_personality() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pirate.toml");
+        std::fs::write(
+            &path,
+            r#"
+[rules]
+warning = "Ahoy! $1."
+
+[messages]
+no-warnings = "Smooth sailing!"
+"#,
+        )
+        .unwrap();
+        let personality = load_personality(path.to_str().unwrap()).unwrap();
+        assert_eq!(personality.rules["warning"], "Ahoy! $1.");
+        assert_eq!(
+            personality.messages["no-warnings"],
+            Message::Text("Smooth sailing!".to_string())
+        );
+    }
+}
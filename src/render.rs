@@ -0,0 +1,925 @@
+//! Renders Clippit's text output as an HTML page, an SVG image, a Markdown document, GitHub
+//! Actions annotations, a SARIF log or a JUnit report, e.g. for a review dashboard, a pull request
+//! comment or CI.
+//!
+//! The HTML and SVG renderers take the same text that is written to the terminal, including its
+//! colors, so the layout of the speech bubble is the same. URLs and `file:line` locations become
+//! links. The other renderers say each diagnostic on its own instead.
+
+use crate::ansi::{self, Segment};
+use crate::character::BubbleStyle;
+use crate::diagnostic::{CrateDiagnostics, Diagnostic, Level};
+use crate::Rewriter;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::LazyLock;
+use unicode_width::UnicodeWidthChar;
+
+/// Returns a self-contained HTML page that shows `text` in a `<pre>`. `bubble` is the style of the
+/// speech bubble in `text`, or `None` if it only has the text without the art.
+pub fn html(text: &str, bubble: Option<BubbleStyle>) -> String {
+    let mut pre = String::new();
+    for line in layout(text, bubble) {
+        for span in line {
+            let mut element = escape(&span.text);
+            let style = span.style.css("color");
+            if span.class.is_some() || !style.is_empty() {
+                let mut attributes = String::new();
+                if let Some(class) = span.class {
+                    write!(attributes, " class=\"{class}\"").unwrap();
+                }
+                if !style.is_empty() {
+                    write!(attributes, " style=\"{style}\"").unwrap();
+                }
+                element = format!("<span{attributes}>{element}</span>");
+            }
+            if let Some(link) = &span.link {
+                element = format!("<a href=\"{}\">{element}</a>", escape(link));
+            }
+            pre.push_str(&element);
+        }
+        pre.push('\n');
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Clippit</title>
+<style>
+body {{ margin: 0; background: {BACKGROUND}; }}
+pre.clippit {{ margin: 0; padding: 1em; color: {FOREGROUND}; font: 14px/1.3 ui-monospace, Menlo, Consolas, monospace; }}
+pre.clippit .art {{ color: {ART}; }}
+pre.clippit .bubble {{ color: {BUBBLE}; }}
+pre.clippit a {{ color: inherit; }}
+</style>
+</head>
+<body>
+<pre class="clippit">{pre}</pre>
+</body>
+</html>
+"#
+    )
+}
+
+/// Returns an SVG image of `text` with one `<text>` element per line. `bubble` is the style of
+/// the speech bubble in `text`, or `None` if it only has the text without the art.
+pub fn svg(text: &str, bubble: Option<BubbleStyle>) -> String {
+    const FONT_SIZE: f32 = 14.0;
+    const COLUMN_WIDTH: f32 = FONT_SIZE * 0.6;
+    const LINE_HEIGHT: f32 = FONT_SIZE * 1.3;
+    const PADDING: f32 = FONT_SIZE;
+
+    let lines = layout(text, bubble);
+    let columns = lines
+        .iter()
+        .filter_map(|line| line.last().map(|span| span.column + span.width()))
+        .max()
+        .unwrap_or_default();
+    let width = PADDING * 2.0 + columns as f32 * COLUMN_WIDTH;
+    let height = PADDING * 2.0 + lines.len() as f32 * LINE_HEIGHT;
+
+    let mut result = String::new();
+    writeln!(
+        result,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.0}" height="{height:.0}" viewBox="0 0 {width:.0} {height:.0}">
+<style>
+text {{ font: {FONT_SIZE}px ui-monospace, Menlo, Consolas, monospace; white-space: pre; fill: {FOREGROUND}; }}
+.art {{ fill: {ART}; }}
+.bubble {{ fill: {BUBBLE}; }}
+a {{ text-decoration: underline; }}
+</style>
+<rect width="100%" height="100%" fill="{BACKGROUND}"/>"#
+    )
+    .unwrap();
+    for (i, line) in lines.iter().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        // The baseline is below the middle of the line
+        let y = PADDING + (i as f32 + 0.75) * LINE_HEIGHT;
+        write!(result, r#"<text y="{y:.1}" xml:space="preserve">"#).unwrap();
+        for span in line {
+            // Each span starts at its column, so that wide characters do not move the text after them
+            let x = PADDING + span.column as f32 * COLUMN_WIDTH;
+            let mut attributes = format!(r#" x="{x:.1}""#);
+            if let Some(class) = span.class {
+                write!(attributes, r#" class="{class}""#).unwrap();
+            }
+            let style = span.style.css("fill");
+            if !style.is_empty() {
+                write!(attributes, r#" style="{style}""#).unwrap();
+            }
+            let tspan = format!("<tspan{attributes}>{}</tspan>", escape(&span.text));
+            match &span.link {
+                Some(link) => write!(result, r#"<a href="{}">{tspan}</a>"#, escape(link)).unwrap(),
+                None => result.push_str(&tspan),
+            }
+        }
+        result.push_str("</text>\n");
+    }
+    result.push_str("</svg>\n");
+    result
+}
+
+const BACKGROUND: &str = "#1e1e1e";
+const FOREGROUND: &str = "#d4d4d4";
+const ART: &str = "#c8c8c8";
+const BUBBLE: &str = "#f5d76e";
+
+/// Returns a Markdown document with `text` in a fenced block, followed by a collapsible
+/// `<details>` section for each warning and error in `diagnostics`, and a table of their counts by
+/// level and lint. `text` is Clippit saying the rest of the output, such as the summary at the end.
+///
+/// The summary of a section is Clippit's phrasing of the diagnostic's first line, and the body is
+/// its source snippet.
+pub fn markdown(text: &str, rewriter: &Rewriter, diagnostics: &[Diagnostic]) -> String {
+    let mut result = String::new();
+    let text = ansi::strip(text);
+    if !text.trim().is_empty() {
+        result.push_str(&fenced(&text));
+    }
+
+    let mut counts: BTreeMap<(Level, &str), usize> = BTreeMap::new();
+    for diagnostic in diagnostics.iter().filter(|d| is_reported(d)) {
+        let code = code(diagnostic).unwrap_or_default();
+        *counts.entry((diagnostic.level, code)).or_default() += 1;
+
+        write!(
+            result,
+            "\n<details>\n<summary>{}</summary>\n\n",
+            escape(&phrase(rewriter, diagnostic))
+        )
+        .unwrap();
+        let snippet = ansi::strip(&diagnostic.snippet()).into_owned();
+        if !snippet.is_empty() {
+            result.push_str(&fenced(&snippet));
+        } else if let Some(span) = diagnostic.primary_span() {
+            // Diagnostics from the JSON without `rendered` still have their location
+            writeln!(
+                result,
+                "`{}:{}:{}`",
+                span.file_name, span.line_start, span.column_start
+            )
+            .unwrap();
+        }
+        result.push_str("</details>\n");
+    }
+
+    if !counts.is_empty() {
+        result.push_str("\n| Level | Lint | Count |\n| --- | --- | ---: |\n");
+        for ((level, code), count) in counts {
+            let code = if code.is_empty() {
+                String::new()
+            } else {
+                format!("`{code}`")
+            };
+            writeln!(result, "| {level} | {code} | {count} |").unwrap();
+        }
+    }
+    result
+}
+
+/// Returns GitHub Actions workflow commands that annotate each warning and error in `diagnostics`
+/// with Clippit's phrasing of it, e.g.
+/// ``::warning file=src/main.rs,line=2,col=9,title=unused_variables::Hmmm... unused variable: `a`.``
+pub fn github(rewriter: &Rewriter, diagnostics: &[Diagnostic]) -> String {
+    let mut result = String::new();
+    for diagnostic in diagnostics.iter().filter(|d| is_reported(d)) {
+        let command = if diagnostic.level == Level::Warning {
+            "warning"
+        } else {
+            "error"
+        };
+        let mut properties = Vec::new();
+        if let Some(span) = diagnostic.primary_span() {
+            properties.push(format!("file={}", escape_property(&span.file_name)));
+            properties.push(format!("line={}", span.line_start));
+            if span.line_end != span.line_start {
+                properties.push(format!("endLine={}", span.line_end));
+            }
+            properties.push(format!("col={}", span.column_start));
+        }
+        if let Some(code) = code(diagnostic) {
+            properties.push(format!("title={}", escape_property(code)));
+        }
+        let properties = if properties.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", properties.join(","))
+        };
+        let message = escape_data(&phrase(rewriter, diagnostic));
+        writeln!(result, "::{command}{properties}::{message}").unwrap();
+    }
+    result
+}
+
+/// Returns a SARIF 2.1.0 log with one run, which has a result for each warning and error in
+/// `diagnostics`. The lints and error codes are the rules of the run. The message of a result is
+/// Clippit's phrasing, and the original message is in its `properties`.
+pub fn sarif(rewriter: &Rewriter, diagnostics: &[Diagnostic]) -> String {
+    let mut rules: Vec<Value> = Vec::new();
+    let mut rule_ids: Vec<&str> = Vec::new();
+    let mut results = Vec::new();
+    for diagnostic in diagnostics.iter().filter(|d| is_reported(d)) {
+        let mut result = json!({
+            "level": if diagnostic.level == Level::Warning { "warning" } else { "error" },
+            "message": { "text": phrase(rewriter, diagnostic) },
+            "properties": { "originalMessage": diagnostic.message },
+        });
+        if let Some(code) = code(diagnostic) {
+            let index = rule_ids
+                .iter()
+                .position(|id| *id == code)
+                .unwrap_or_else(|| {
+                    let mut rule = json!({ "id": code });
+                    if let Some(uri) = help_uri(diagnostic) {
+                        rule["helpUri"] = json!(uri);
+                    }
+                    rules.push(rule);
+                    rule_ids.push(code);
+                    rule_ids.len() - 1
+                });
+            result["ruleId"] = json!(code);
+            result["ruleIndex"] = json!(index);
+        }
+        if let Some(span) = diagnostic.primary_span() {
+            result["locations"] = json!([{
+                "physicalLocation": {
+                    "artifactLocation": { "uri": span.file_name },
+                    "region": {
+                        "startLine": span.line_start,
+                        "startColumn": span.column_start,
+                        "endLine": span.line_end,
+                        "endColumn": span.column_end,
+                    },
+                },
+            }]);
+        }
+        results.push(result);
+    }
+
+    let log = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "clippit",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                },
+            },
+            // rustc counts the columns in characters
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }],
+    });
+    serde_json::to_string_pretty(&log).unwrap() + "\n"
+}
+
+/// Returns a JUnit XML report with a `<testsuite>` for each crate in `crates` and a `<testcase>`
+/// for each warning and error. Errors are failures, and so are warnings, unless
+/// `warnings_skipped` makes them skipped test cases. Each has Clippit's phrasing and the source
+/// snippet.
+pub fn junit(rewriter: &Rewriter, crates: &[CrateDiagnostics], warnings_skipped: bool) -> String {
+    let mut suites = String::new();
+    let (mut all_tests, mut all_failures, mut all_skipped) = (0, 0, 0);
+    for krate in crates {
+        // Diagnostics without a crate come from rustc on its own
+        let name = escape(if krate.name.is_empty() {
+            "rustc"
+        } else {
+            &krate.name
+        });
+        let mut cases = String::new();
+        let (mut tests, mut failures, mut skipped) = (0, 0, 0);
+        for diagnostic in krate.diagnostics.iter().filter(|d| is_reported(d)) {
+            tests += 1;
+            // Only failures have a type
+            let (element, kind) = if warnings_skipped && diagnostic.level == Level::Warning {
+                skipped += 1;
+                ("skipped", String::new())
+            } else {
+                failures += 1;
+                ("failure", format!(r#" type="{}""#, diagnostic.level))
+            };
+            let phrase = phrase(rewriter, diagnostic);
+            let snippet = ansi::strip(&diagnostic.snippet()).into_owned();
+            let body = if snippet.is_empty() {
+                phrase.clone()
+            } else {
+                format!("{phrase}\n{snippet}")
+            };
+            writeln!(
+                cases,
+                r#"    <testcase classname="{name}" name="{}">
+      <{element} message="{}"{kind}>{}</{element}>
+    </testcase>"#,
+                escape(&testcase_name(diagnostic)),
+                escape(&phrase),
+                escape(&body)
+            )
+            .unwrap();
+        }
+        writeln!(
+            suites,
+            r#"  <testsuite name="{name}" tests="{tests}" failures="{failures}" errors="0" skipped="{skipped}">"#
+        )
+        .unwrap();
+        suites.push_str(&cases);
+        suites.push_str("  </testsuite>\n");
+        all_tests += tests;
+        all_failures += failures;
+        all_skipped += skipped;
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="clippit" tests="{all_tests}" failures="{all_failures}" errors="0" skipped="{all_skipped}">
+{suites}</testsuites>
+"#
+    )
+}
+
+/// Returns the name of the test case for `diagnostic`, e.g.
+/// `clippy::double_parens at src/main.rs:2:20`.
+fn testcase_name(diagnostic: &Diagnostic) -> String {
+    let code = code(diagnostic).map_or_else(|| diagnostic.level.to_string(), str::to_string);
+    match diagnostic.primary_span() {
+        Some(span) => format!(
+            "{code} at {}:{}:{}",
+            span.file_name, span.line_start, span.column_start
+        ),
+        None => format!("{code}: {}", diagnostic.message),
+    }
+}
+
+/// Returns the URL from the "for further information visit" line of a clippy lint.
+fn help_uri(diagnostic: &Diagnostic) -> Option<&str> {
+    diagnostic
+        .help()
+        .chain(diagnostic.notes())
+        .find_map(|message| message.strip_prefix("for further information visit "))
+        .map(str::trim)
+}
+
+/// Returns `true` for the warnings and errors, which get a section or an annotation of their own.
+fn is_reported(diagnostic: &Diagnostic) -> bool {
+    !diagnostic.is_summary()
+        && matches!(
+            diagnostic.level,
+            Level::InternalCompilerError | Level::Error | Level::Warning
+        )
+}
+
+/// Returns the lint name or the error code of `diagnostic`.
+fn code(diagnostic: &Diagnostic) -> Option<&str> {
+    diagnostic.lint_name().or_else(|| diagnostic.error_code())
+}
+
+/// Returns Clippit's phrasing of the first line of `diagnostic`, without colors.
+fn phrase(rewriter: &Rewriter, diagnostic: &Diagnostic) -> String {
+    let first_line = diagnostic.message.lines().next().unwrap_or_default();
+    // The header as rustc prints it, so that the rules for error codes apply
+    let header = match diagnostic.error_code() {
+        Some(error_code) => format!("{}[{error_code}]: {first_line}", diagnostic.level),
+        None => format!("{}: {first_line}", diagnostic.level),
+    };
+    let phrase = rewriter.rewrite_line(
+        &header,
+        Some(diagnostic.level),
+        diagnostic.lint_name(),
+        &mut [],
+        rewriter.pick(),
+    );
+    ansi::strip(phrase.lines().next().unwrap_or_default()).into_owned()
+}
+
+/// Escapes the message of a workflow command.
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a property of a workflow command, such as `file=`.
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Returns `s` in a fenced code block, with a fence that is longer than the backticks in `s`.
+fn fenced(s: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in s.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let fence = "`".repeat(longest.max(2) + 1);
+    let newline = if s.ends_with('\n') { "" } else { "\n" };
+    format!("{fence}text\n{s}{newline}{fence}\n")
+}
+
+/// Text with the same colors, class and link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Span {
+    /// Column where the text starts
+    column: usize,
+    text: String,
+    style: Sgr,
+    /// `art` or `bubble`
+    class: Option<&'static str>,
+    link: Option<String>,
+}
+
+impl Span {
+    fn width(&self) -> usize {
+        self.text.chars().filter_map(UnicodeWidthChar::width).sum()
+    }
+}
+
+/// Splits each line of `text` into spans.
+fn layout(text: &str, bubble: Option<BubbleStyle>) -> Vec<Vec<Span>> {
+    let mut style = Sgr::default();
+    let mut result = Vec::new();
+    for line in text.lines() {
+        // Each character of the line without escape sequences, with its style
+        let mut chars = Vec::new();
+        for segment in ansi::segments(line) {
+            match segment {
+                Segment::Escape(escape) => style.apply(escape),
+                Segment::Text(text) => chars.extend(text.chars().map(|c| (c, style.clone()))),
+            }
+        }
+        let plain: String = chars.iter().map(|(c, _)| c).collect();
+        let border = bubble.map(|bubble| bubble_border(&plain, bubble));
+        let last = chars.len().saturating_sub(1);
+        let class = |i: usize| match border {
+            Some(Border::Line) => Some("bubble"),
+            Some(Border::Sides) if i == 0 || i == last => Some("bubble"),
+            Some(Border::Sides) => None,
+            Some(Border::None) => Some("art"),
+            None => None,
+        };
+        let links = links(&plain);
+
+        let mut spans: Vec<Span> = Vec::new();
+        let (mut column, mut offset) = (0, 0);
+        for (i, (c, style)) in chars.into_iter().enumerate() {
+            let link = links
+                .iter()
+                .find(|(range, _)| range.contains(&offset))
+                .map(|(_, link)| link.clone());
+            let class = class(i);
+            match spans.last_mut() {
+                Some(span) if span.style == style && span.class == class && span.link == link => {
+                    span.text.push(c);
+                }
+                _ => spans.push(Span {
+                    column,
+                    text: c.to_string(),
+                    style,
+                    class,
+                    link,
+                }),
+            }
+            column += c.width().unwrap_or_default();
+            offset += c.len_utf8();
+        }
+        result.push(spans);
+    }
+    result
+}
+
+/// Which characters of a line are the border of the speech bubble.
+enum Border {
+    /// The top or the bottom of the bubble
+    Line,
+    /// The first and the last character of a line in the bubble
+    Sides,
+    /// The line is not in the bubble, so it is part of the art
+    None,
+}
+
+fn bubble_border(line: &str, bubble: BubbleStyle) -> Border {
+    let chars = bubble.chars();
+    let first = line.chars().next();
+    let last = line.chars().last();
+    if line.chars().count() < 2 {
+        Border::None
+    } else if first == Some(chars.top_left) && last == Some(chars.top_right)
+        || first == Some(chars.bottom_left) && last == Some(chars.bottom_right)
+    {
+        // The top border has a gap where the tail attaches
+        let is_border = line.chars().all(|c| {
+            [chars.top, chars.bottom, ' '].contains(&c) || c == first.unwrap() || c == last.unwrap()
+        });
+        if is_border {
+            Border::Line
+        } else {
+            Border::None
+        }
+    } else if first == Some(chars.side) && last == Some(chars.side) {
+        Border::Sides
+    } else {
+        Border::None
+    }
+}
+
+/// Returns the byte ranges of the URLs and `file:line` locations in `line`, with their links.
+/// Locations link to the file relative to the page, with the line as a `#L<line>` anchor.
+fn links(line: &str) -> Vec<(std::ops::Range<usize>, String)> {
+    static LINK_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r#"https?://[^\s<>"'`]*[^\s<>"'`.,;:!?)]|([\w./-]*\w\.rs):(\d+)(?::\d+)?"#)
+            .unwrap()
+    });
+
+    LINK_REGEX
+        .captures_iter(line)
+        .map(|captures| {
+            let range = captures.get(0).unwrap().range();
+            let link = match (captures.get(1), captures.get(2)) {
+                (Some(file), Some(line)) => format!("{}#L{}", file.as_str(), line.as_str()),
+                _ => captures[0].to_string(),
+            };
+            (range, link)
+        })
+        .collect()
+}
+
+/// The style that SGR sequences such as `\x1b[1;33m` set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Sgr {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    color: Option<String>,
+    background: Option<String>,
+}
+
+impl Sgr {
+    fn apply(&mut self, escape: &str) {
+        let Some(parameters) = escape
+            .strip_prefix("\x1b[")
+            .and_then(|s| s.strip_suffix('m'))
+        else {
+            return;
+        };
+        let mut parameters = parameters
+            .split(';')
+            .map(|p| p.parse::<u8>().unwrap_or_default());
+        while let Some(parameter) = parameters.next() {
+            match parameter {
+                0 => *self = Sgr::default(),
+                1 => self.bold = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                22 => self.bold = false,
+                23 => self.italic = false,
+                24 => self.underline = false,
+                30..=37 => self.color = Some(palette(parameter - 30)),
+                90..=97 => self.color = Some(palette(parameter - 90 + 8)),
+                40..=47 => self.background = Some(palette(parameter - 40)),
+                100..=107 => self.background = Some(palette(parameter - 100 + 8)),
+                38 => self.color = extended_color(&mut parameters),
+                48 => self.background = extended_color(&mut parameters),
+                39 => self.color = None,
+                49 => self.background = None,
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns the CSS declarations of the style, with the text color as `color_property`.
+    fn css(&self, color_property: &str) -> String {
+        let mut result = String::new();
+        if self.bold {
+            result.push_str("font-weight:bold;");
+        }
+        if self.italic {
+            result.push_str("font-style:italic;");
+        }
+        if self.underline {
+            result.push_str("text-decoration:underline;");
+        }
+        if let Some(color) = &self.color {
+            write!(result, "{color_property}:{color};").unwrap();
+        }
+        if let Some(background) = &self.background {
+            write!(result, "background:{background};").unwrap();
+        }
+        result
+    }
+}
+
+/// Reads the rest of `38;5;<n>` or `38;2;<r>;<g>;<b>`.
+fn extended_color(parameters: &mut impl Iterator<Item = u8>) -> Option<String> {
+    match parameters.next()? {
+        5 => Some(palette(parameters.next()?)),
+        2 => {
+            let (r, g, b) = (parameters.next()?, parameters.next()?, parameters.next()?);
+            Some(format!("#{r:02x}{g:02x}{b:02x}"))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the color of the 256-color palette, with the 16 colors of the terminal first.
+fn palette(n: u8) -> String {
+    const COLORS: [&str; 16] = [
+        "#000000", "#cd3131", "#0dbc79", "#e5e510", "#2472c8", "#bc3fbc", "#11a8cd", "#e5e5e5",
+        "#666666", "#f14c4c", "#23d18b", "#f5f543", "#3b8eea", "#d670d6", "#29b8db", "#ffffff",
+    ];
+    match n {
+        0..=15 => COLORS[n as usize].to_string(),
+        16..=231 => {
+            let level = |i: u8| if i == 0 { 0 } else { 55 + i * 40 };
+            let n = n - 16;
+            format!(
+                "#{:02x}{:02x}{:02x}",
+                level(n / 36),
+                level(n / 6 % 6),
+                level(n % 6)
+            )
+        }
+        _ => {
+            let gray = 8 + (n - 232) * 10;
+            format!("#{gray:02x}{gray:02x}{gray:02x}")
+        }
+    }
+}
+
+/// Escapes text for HTML and XML.
+fn escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const OUTPUT: &str = "   /‾‾\\
+     /\\
+/‾‾‾‾  ‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\\
+| \x1b[1m\x1b[33mHmmm... a < b.\x1b[0m       |
+|  --> src/main.rs:4:9 |
+\\______________________/
+";
+
+    #[test]
+    fn test_html() {
+        let page = html(OUTPUT, Some(BubbleStyle::Classic));
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        let pre = &page[page.find("<pre").unwrap()..page.find("</pre>").unwrap() + 6];
+        assert_eq!(
+            pre,
+            r#"<pre class="clippit"><span class="art">   /‾‾\</span>
+<span class="art">     /\</span>
+<span class="bubble">/‾‾‾‾  ‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\</span>
+<span class="bubble">|</span> <span style="font-weight:bold;color:#e5e510;">Hmmm... a &lt; b.</span>       <span class="bubble">|</span>
+<span class="bubble">|</span>  --&gt; <a href="src/main.rs#L4">src/main.rs:4:9</a> <span class="bubble">|</span>
+<span class="bubble">\______________________/</span>
+</pre>"#
+        );
+
+        // Without the art, there is no bubble
+        assert!(html("a\n", None).contains("<pre class=\"clippit\">a\n</pre>"));
+    }
+
+    #[test]
+    fn test_svg() {
+        let image = svg(OUTPUT, Some(BubbleStyle::Classic));
+        assert!(image.starts_with(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="230" height="137" viewBox="0 0 230 137">"#
+        ));
+        assert!(image.contains(
+            r#"<text y="64.0" xml:space="preserve"><tspan x="14.0" class="bubble">/‾‾‾‾  ‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\</tspan></text>"#
+        ));
+        assert!(image.contains(
+            r#"<tspan x="22.4"> </tspan><tspan x="30.8" style="font-weight:bold;fill:#e5e510;">Hmmm... a &lt; b.</tspan>"#
+        ));
+        assert!(image
+            .contains(r#"<a href="src/main.rs#L4"><tspan x="72.8">src/main.rs:4:9</tspan></a>"#));
+        assert!(image.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn test_markdown() {
+        let diagnostics = crate::diagnostic::parse_human(
+            "warning: unused variable: `a`
+ --> src/main.rs:2:9
+  |
+2 |     let a = 0;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_a`
+  |
+  = note: `#[warn(unused_variables)]` on by default
+
+warning: `playground` (bin \"playground\") generated 1 warning
+",
+        );
+        assert_eq!(
+            markdown(
+                "\x1b[1mWoohoo!\x1b[0m\n",
+                &Rewriter::default(),
+                &diagnostics
+            ),
+            r#"```text
+Woohoo!
+```
+
+<details>
+<summary>Hmmm... unused variable: `a`.</summary>
+
+```text
+ --> src/main.rs:2:9
+  |
+2 |     let a = 0;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_a`
+  |
+```
+</details>
+
+| Level | Lint | Count |
+| --- | --- | ---: |
+| warning | `unused_variables` | 1 |
+"#
+        );
+
+        // The fence is longer than the backticks in the text
+        assert_eq!(fenced("a ```b``` c"), "````text\na ```b``` c\n````\n");
+        assert_eq!(markdown("", &Rewriter::default(), &[]), "");
+    }
+
+    #[test]
+    fn test_github() {
+        let diagnostics = crate::diagnostic::parse_human(
+            "warning: unused variable: `a`
+ --> src/main.rs:2:9
+  |
+2 |     let a = 0;
+  |         ^
+  |
+  = note: `#[warn(unused_variables)]` on by default
+
+error: 100% broken, see: a,b
+",
+        );
+        assert_eq!(
+            github(&Rewriter::default(), &diagnostics),
+            "::warning file=src/main.rs,line=2,col=9,title=unused_variables::Hmmm... unused variable: `a`.
+::error::Hmmm... 100%25 broken, see: a,b.
+"
+        );
+    }
+
+    #[test]
+    fn test_sarif() {
+        let diagnostics = crate::diagnostic::parse_human(
+            "warning: consider removing unnecessary double parentheses
+ --> src/main.rs:2:20
+  |
+2 |     println!(\"{}\", ((0)));
+  |                    ^^^^^
+  |
+  = help: for further information visit https://rust-lang.github.io/rust-clippy/master/index.html#double_parens
+  = note: `#[warn(clippy::double_parens)]` on by default
+
+warning: `playground` (bin \"playground\") generated 1 warning
+",
+        );
+        let log: Value = serde_json::from_str(&sarif(&Rewriter::default(), &diagnostics)).unwrap();
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "clippit");
+        assert_eq!(
+            run["tool"]["driver"]["rules"],
+            json!([{
+                "id": "clippy::double_parens",
+                "helpUri": "https://rust-lang.github.io/rust-clippy/master/index.html#double_parens",
+            }])
+        );
+        assert_eq!(
+            run["results"],
+            json!([{
+                "ruleId": "clippy::double_parens",
+                "ruleIndex": 0,
+                "level": "warning",
+                "message": { "text": "Hmmm... consider removing unnecessary double parentheses." },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": "src/main.rs" },
+                        "region": { "startLine": 2, "startColumn": 20, "endLine": 2, "endColumn": 25 },
+                    },
+                }],
+                "properties": { "originalMessage": "consider removing unnecessary double parentheses" },
+            }])
+        );
+    }
+
+    #[rstest]
+    #[case(false, "failure", r#"<failure message="Hmmm... consider removing unnecessary double parentheses." type="warning">"#, "</failure>")]
+    #[case(
+        true,
+        "skipped",
+        r#"<skipped message="Hmmm... consider removing unnecessary double parentheses.">"#,
+        "</skipped>"
+    )]
+    fn test_junit(
+        #[case] warnings_skipped: bool,
+        #[case] counts: &str,
+        #[case] start: &str,
+        #[case] end: &str,
+    ) {
+        let crates = crate::diagnostic::parse_crates(
+            "    Checking playground v0.0.1 (/playground)
+warning: consider removing unnecessary double parentheses
+ --> src/main.rs:2:20
+  |
+2 |     println!(\"{}\", ((0)));
+  |                    ^^^^^
+  |
+  = help: for further information visit https://rust-lang.github.io/rust-clippy/master/index.html#double_parens
+  = note: `#[warn(clippy::double_parens)]` on by default
+
+    Checking other v0.1.0 (/playground/other)
+",
+        );
+        let counts = if counts == "failure" {
+            r#"tests="1" failures="1" errors="0" skipped="0""#
+        } else {
+            r#"tests="1" failures="0" errors="0" skipped="1""#
+        };
+        assert_eq!(
+            junit(&Rewriter::default(), &crates, warnings_skipped),
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="clippit" {counts}>
+  <testsuite name="playground" {counts}>
+    <testcase classname="playground" name="clippy::double_parens at src/main.rs:2:20">
+      {start}Hmmm... consider removing unnecessary double parentheses.
+ --&gt; src/main.rs:2:20
+  |
+2 |     println!(&quot;{{}}&quot;, ((0)));
+  |                    ^^^^^
+  |
+{end}
+    </testcase>
+  </testsuite>
+  <testsuite name="other" tests="0" failures="0" errors="0" skipped="0">
+  </testsuite>
+</testsuites>
+"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_escape_property() {
+        assert_eq!(escape_property("C:\\a,b%\n"), "C%3A\\a%2Cb%25%0A");
+    }
+
+    #[rstest]
+    #[case("see https://example.com/a#b.", &[(4..27, "https://example.com/a#b")])]
+    #[case(" --> src/lib.rs:12:5", &[(5..20, "src/lib.rs#L12")])]
+    #[case("a::b::c: 1.0", &[])]
+    fn test_links(#[case] line: &str, #[case] expected: &[(std::ops::Range<usize>, &str)]) {
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|(range, link)| (range.clone(), link.to_string()))
+            .collect();
+        assert_eq!(links(line), expected);
+    }
+
+    #[rstest]
+    #[case("\x1b[1;31m", "font-weight:bold;color:#cd3131;")]
+    #[case("\x1b[38;5;12m", "color:#3b8eea;")]
+    #[case("\x1b[38;5;196m", "color:#ff0000;")]
+    #[case("\x1b[38;5;244m", "color:#808080;")]
+    #[case("\x1b[38;2;1;2;3m\x1b[4m", "text-decoration:underline;color:#010203;")]
+    #[case("\x1b[1m\x1b[0m", "")]
+    fn test_sgr(#[case] escapes: &str, #[case] expected: &str) {
+        let mut sgr = Sgr::default();
+        for segment in ansi::segments(escapes) {
+            if let Segment::Escape(escape) = segment {
+                sgr.apply(escape);
+            }
+        }
+        assert_eq!(sgr.css("color"), expected);
+    }
+}
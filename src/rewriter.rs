@@ -0,0 +1,1196 @@
+use crate::ansi;
+use crate::diagnostic::{parse_human_blocks, parse_json, Block, Diagnostic, Level};
+use crate::dismissed::Dismissed;
+use crate::explain::{clippy_lint_in, explain_lint, ErrorExplainer};
+use crate::locale::Catalog;
+use crate::message::{package_description, package_name, parse_messages, Message, Target};
+use crate::rules::{builtin_rules, load_rules, Rule};
+use anyhow::{Context, Result};
+use regex::{Regex, RegexSet};
+use regex_syntax::hir::literal::{ExtractKind, Extractor};
+use regex_syntax::hir::Look;
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::mem::take;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Rewrites cargo's output to sound like Clippit using a set of `Rule`s.
+///
+/// The output is rewritten line by line. Diagnostics are first put into the form that rustc prints
+/// them in, so that the rules do not depend on whether the input was human-readable or JSON.
+pub struct Rewriter {
+    rules: Vec<CompiledRule>,
+    /// Matches all rules' patterns at once, so that lines that no rule matches are only scanned once
+    regex_set: RegexSet,
+    quiet: bool,
+    dismissed: Dismissed,
+    explain_lints: bool,
+    explain_errors: bool,
+    errors: ErrorExplainer,
+    catalog: Catalog,
+    /// Picks the phrasing of rules with alternatives, or `None` to always use `replacement`
+    random: Option<Random>,
+}
+
+struct CompiledRule {
+    rule: Rule,
+    regex: Regex,
+    /// Lines must start with one of these for the rule to match, which is much faster to check
+    /// than running the regex
+    prefixes: Option<Vec<Vec<u8>>>,
+}
+
+impl Rewriter {
+    /// Compiles the enabled rules.
+    pub fn new(mut rules: Vec<Rule>) -> Result<Self> {
+        rules.retain(|rule| rule.enabled);
+        rules.sort_by_key(|rule| Reverse(rule.priority));
+
+        let rules: Vec<CompiledRule> = rules
+            .into_iter()
+            .map(|rule| {
+                if rule.pattern.is_empty() {
+                    anyhow::bail!("rule \"{}\" has no pattern", rule.id);
+                }
+                let regex = Regex::new(&rule.pattern)
+                    .with_context(|| format!("invalid pattern in rule \"{}\"", rule.id))?;
+                let prefixes = literal_prefixes(&rule.pattern);
+                Ok(CompiledRule {
+                    rule,
+                    regex,
+                    prefixes,
+                })
+            })
+            .collect::<Result<_>>()?;
+        let regex_set = RegexSet::new(rules.iter().map(|compiled| &compiled.rule.pattern))?;
+        Ok(Self {
+            rules,
+            regex_set,
+            quiet: false,
+            dismissed: Dismissed::default(),
+            explain_lints: false,
+            explain_errors: false,
+            errors: ErrorExplainer::default(),
+            catalog: Catalog::default(),
+            random: None,
+        })
+    }
+
+    /// Returns a `Rewriter` with the built-in rules and the rules from the user's rule files.
+    /// `config` replaces `./clippit.toml`.
+    pub fn load(config: Option<&Path>) -> Result<Self> {
+        Self::new(load_rules(config)?)
+    }
+
+    /// Only phrases diagnostics if `quiet` is `true`. Lines outside of diagnostics, such as
+    /// "Checking ..." and "Woohoo, no warnings!", are left out.
+    pub fn set_quiet(&mut self, quiet: bool) {
+        self.quiet = quiet;
+    }
+
+    /// Leaves out the diagnostics of the lints and error codes in `dismissed`. They still count
+    /// towards the summary, and Clippit says how many were left out at the end.
+    pub fn set_dismissed(&mut self, dismissed: Dismissed) {
+        self.dismissed = dismissed;
+    }
+
+    /// Explains the clippy lint of a diagnostic below it if `explain_lints` is `true`. Each lint is
+    /// only explained below its first diagnostic.
+    pub fn set_explain_lints(&mut self, explain_lints: bool) {
+        self.explain_lints = explain_lints;
+    }
+
+    /// Explains the error code of a diagnostic below it with `rustc --explain` if
+    /// `explain_errors` is `true`. Each error code is only explained below its first diagnostic.
+    pub fn set_explain_errors(&mut self, explain_errors: bool) {
+        self.explain_errors = explain_errors;
+    }
+
+    /// Uses `errors` instead of `rustc --explain` to explain error codes.
+    pub fn set_error_explainer(&mut self, errors: ErrorExplainer) {
+        self.errors = errors;
+    }
+
+    /// Picks one of the phrasings of rules with `alternatives` at random for each diagnostic, and
+    /// for each line outside of diagnostics. All lines of a diagnostic use the same pick, so that
+    /// their phrasings go together. The same seed picks the same phrasings for the same output.
+    /// Without a seed, rules always use their `replacement`.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.random = Some(Random(AtomicU64::new(seed)));
+    }
+
+    /// Phrases the output in the language of `catalog`. Rules that the user replaced keep their
+    /// replacement, since only the built-in replacements are translated.
+    pub fn set_catalog(&mut self, catalog: Catalog) {
+        let builtin = builtin_rules();
+        for CompiledRule { rule, .. } in &mut self.rules {
+            let english = builtin.iter().find(|builtin| builtin.id == rule.id);
+            let is_phrased = english.is_some_and(|english| english.replacement == rule.replacement)
+                || self.catalog.rule_replacement(&rule.id) == Some(&rule.replacement);
+            if !is_phrased {
+                continue;
+            }
+            // The alternatives are only in English
+            if let Some(replacement) = catalog.rule_replacement(&rule.id) {
+                rule.replacement = replacement.to_string();
+                rule.alternatives.clear();
+            } else if let Some(english) = english {
+                rule.replacement.clone_from(&english.replacement);
+                rule.alternatives.clone_from(&english.alternatives);
+            }
+        }
+        self.catalog = catalog;
+    }
+
+    /// Returns the phrases of the language that the output is phrased in.
+    pub fn catalog(&self) -> &Catalog {
+        &self.catalog
+    }
+
+    /// Explains an error code such as `E0597` with `rustc --explain`. The explanations are
+    /// remembered, so that rustc is only run once for each error code.
+    pub fn explain_error(&self, code: &str) -> Option<String> {
+        self.errors.explain(code, &self.catalog)
+    }
+
+    /// Replaces words in cargo's human-readable output.
+    pub fn replace_words(&self, s: &str) -> String {
+        concat(&self.words_parts(s))
+    }
+
+    fn words_parts(&self, s: &str) -> Vec<Part> {
+        let mut pass = Pass::new(self);
+        for block in parse_human_blocks(s) {
+            match block {
+                Block::Diagnostic(diagnostic) => pass.push_diagnostic(&diagnostic),
+                Block::Line(line) => pass.push_line(&line, None, None),
+            }
+        }
+        pass.finish()
+    }
+
+    /// Phrases the records from `cargo clippy --message-format=json`.
+    pub fn replace_messages(&self, messages: &[Message]) -> String {
+        concat(&self.messages_parts(messages))
+    }
+
+    fn messages_parts(&self, messages: &[Message]) -> Vec<Part> {
+        let mut stream = self.message_stream("clippy");
+        let mut result = Vec::new();
+        for message in messages {
+            result.extend(stream.push(message));
+        }
+        result.extend(stream.finish());
+        result
+    }
+
+    /// Returns a `MessageStream` to phrase the records from e.g. `cargo clippy
+    /// --message-format=json` while cargo is still running. `subcommand` is the cargo subcommand
+    /// that produces the records, such as `"clippy"` or `"build"`.
+    pub fn message_stream(&self, subcommand: &str) -> MessageStream<'_> {
+        MessageStream {
+            pass: Pass::new(self),
+            status: status(subcommand),
+            checked_packages: Vec::new(),
+            counts: Vec::new(),
+            is_finished: false,
+            said_finished: false,
+        }
+    }
+
+    /// Phrases diagnostics, e.g. after filtering the output of `diagnostic::parse_human()`.
+    pub fn replace_diagnostics(&self, diagnostics: &[Diagnostic]) -> String {
+        concat(&self.diagnostics_parts(diagnostics))
+    }
+
+    fn diagnostics_parts(&self, diagnostics: &[Diagnostic]) -> Vec<Part> {
+        let mut pass = Pass::new(self);
+        for diagnostic in diagnostics {
+            pass.push_diagnostic(diagnostic);
+        }
+        pass.finish()
+    }
+
+    /// Phrases the output of cargo or rustc, which can be human-readable or JSON from
+    /// `--message-format=json` or `--error-format=json`.
+    pub fn replace_output(&self, input: &str) -> String {
+        concat(&self.replace_output_parts(input))
+    }
+
+    /// Same as `replace_output()`, but each diagnostic is a separate `Part`.
+    pub fn replace_output_parts(&self, input: &str) -> Vec<Part> {
+        // With `2>&1`, cargo's human-readable status lines are mixed in with the JSON
+        if !input.lines().any(|line| line.starts_with("{\"")) {
+            return self.words_parts(input);
+        }
+
+        let messages = parse_messages(input);
+        if messages
+            .iter()
+            .any(|message| !matches!(message, Message::Unknown))
+        {
+            self.messages_parts(&messages)
+        } else {
+            self.diagnostics_parts(&parse_json(input))
+        }
+    }
+
+    /// Returns a random number for picking phrasings, or `None` without a seed.
+    pub(crate) fn pick(&self) -> Option<u64> {
+        self.random.as_ref().map(Random::next)
+    }
+
+    /// Rewrites a line with the first rule that matches it. `pick` is from `pick()`.
+    ///
+    /// `applied` is whether each rule has been applied, for the rules with `once`. An empty slice
+    /// rewrites `line` on its own, e.g. the header of a diagnostic.
+    pub(crate) fn rewrite_line<'l>(
+        &self,
+        line: &'l str,
+        level: Option<Level>,
+        lint: Option<&str>,
+        applied: &mut [bool],
+        pick: Option<u64>,
+    ) -> Cow<'l, str> {
+        if !self.regex_set.is_match(line) {
+            return Cow::Borrowed(line);
+        }
+
+        for (i, compiled) in self.rules.iter().enumerate() {
+            if (compiled.rule.once && applied.get(i) == Some(&true))
+                || !compiled.rule.applies_to(level, lint)
+                || compiled.prefixes.as_ref().is_some_and(|prefixes| {
+                    !prefixes
+                        .iter()
+                        .any(|prefix| line.as_bytes().starts_with(prefix))
+                })
+                || !compiled.regex.is_match(line)
+            {
+                continue;
+            }
+
+            if let Some(applied) = applied.get_mut(i) {
+                *applied = true;
+            }
+            return compiled.regex.replace(line, phrasing(&compiled.rule, pick));
+        }
+        Cow::Borrowed(line)
+    }
+}
+
+/// Returns the replacement of `rule`, or one of its alternatives with a `pick` from
+/// `Rewriter::pick()`.
+fn phrasing(rule: &Rule, pick: Option<u64>) -> &str {
+    match pick {
+        Some(pick) if !rule.alternatives.is_empty() => {
+            match (pick % (rule.alternatives.len() as u64 + 1)) as usize {
+                0 => &rule.replacement,
+                i => &rule.alternatives[i - 1],
+            }
+        }
+        _ => &rule.replacement,
+    }
+}
+
+impl Default for Rewriter {
+    /// Returns a `Rewriter` with the built-in rules.
+    fn default() -> Self {
+        Self::new(builtin_rules()).unwrap()
+    }
+}
+
+/// A part of the rewritten output. Each diagnostic is a separate part, so that Clippit can say it
+/// in its own speech bubble.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Part {
+    /// The lines of one diagnostic.
+    Diagnostic(String),
+    /// Lines outside of diagnostics, such as "I'm checking ..." and the summary at the end.
+    Text(String),
+}
+
+impl Part {
+    pub fn text(&self) -> &str {
+        match self {
+            Part::Diagnostic(text) | Part::Text(text) => text,
+        }
+    }
+}
+
+/// Joins the lines of `parts`.
+fn concat(parts: &[Part]) -> String {
+    parts.iter().map(Part::text).collect()
+}
+
+/// Rewrites one output of cargo.
+struct Pass<'a> {
+    rewriter: &'a Rewriter,
+    /// Whether each rule has been applied, so that rules with `once` are only applied once
+    applied: Vec<bool>,
+    no_warnings: bool,
+    parts: Vec<Part>,
+    /// Whether lines are added to the last part, which is a diagnostic
+    in_diagnostic: bool,
+    /// Number of diagnostics that were left out because the user dismissed them
+    hidden: usize,
+    /// Clippy lints and error codes that have been explained
+    explained: Vec<String>,
+    /// The pick of phrasings for the lines of the current diagnostic
+    pick: Option<u64>,
+}
+
+impl<'a> Pass<'a> {
+    fn new(rewriter: &'a Rewriter) -> Self {
+        Self {
+            rewriter,
+            applied: vec![false; rewriter.rules.len()],
+            no_warnings: true,
+            parts: Vec::new(),
+            in_diagnostic: false,
+            hidden: 0,
+            explained: Vec::new(),
+            pick: None,
+        }
+    }
+
+    fn push_line(&mut self, line: &str, level: Option<Level>, lint: Option<&str>) {
+        if self.rewriter.quiet && level.is_none() {
+            return;
+        }
+        // Rules match colored lines without their colors. Lines that no rule rewrites keep all of
+        // their colors, and rewritten lines keep the color that they start with.
+        let plain = ansi::strip(line);
+        let pick = if self.in_diagnostic {
+            self.pick
+        } else {
+            self.rewriter.pick()
+        };
+        let mut line =
+            match self
+                .rewriter
+                .rewrite_line(&plain, level, lint, &mut self.applied, pick)
+            {
+                Cow::Borrowed(_) => line.to_string(),
+                Cow::Owned(rewritten) if plain.len() < line.len() => {
+                    ansi::restyle(line, &rewritten)
+                }
+                Cow::Owned(rewritten) => rewritten,
+            };
+        line.push('\n');
+        self.push_str(&line);
+    }
+
+    /// Adds text to the current diagnostic, or else to the text outside of diagnostics.
+    fn push_str(&mut self, s: &str) {
+        match self.parts.last_mut() {
+            Some(Part::Diagnostic(text)) if self.in_diagnostic => text.push_str(s),
+            Some(Part::Text(text)) if !self.in_diagnostic => text.push_str(s),
+            _ => self.parts.push(Part::Text(s.to_string())),
+        }
+    }
+
+    /// Rewrites a diagnostic. The header and the notes are written from the diagnostic's fields,
+    /// and the source snippets are taken from the rendered diagnostic.
+    fn push_diagnostic(&mut self, diagnostic: &Diagnostic) {
+        let level = Some(diagnostic.level);
+        let lint = diagnostic.lint_name();
+        if matches!(diagnostic.level, Level::Error | Level::Warning) {
+            self.no_warnings = false;
+        }
+        if diagnostic
+            .lint_name()
+            .or_else(|| diagnostic.error_code())
+            .is_some_and(|code| self.rewriter.dismissed.contains(code))
+        {
+            self.hidden += 1;
+            return;
+        }
+
+        let header = match diagnostic.error_code() {
+            Some(code) => format!("{}[{code}]: {}", diagnostic.level, diagnostic.message),
+            None => format!("{}: {}", diagnostic.level, diagnostic.message),
+        };
+
+        // Summaries such as "aborting due to previous error" are not diagnostics of their own
+        if !diagnostic.is_summary() {
+            self.parts.push(Part::Diagnostic(String::new()));
+            self.in_diagnostic = true;
+            self.pick = self.rewriter.pick();
+        }
+
+        match &diagnostic.rendered {
+            Some(rendered) => {
+                // Continuation lines of a multi-line message are in the rendered diagnostic
+                let header = header.lines().next().unwrap_or("");
+                match rendered.lines().next() {
+                    // The header is taken from a colored diagnostic to keep its colors
+                    Some(colored) if colored.contains('\x1b') && ansi::strip(colored) == header => {
+                        self.push_line(colored, level, lint);
+                    }
+                    _ => self.push_line(header, level, lint),
+                }
+                for line in rendered.lines().skip(1) {
+                    // The gutter is wider if the line numbers have more digits
+                    match line.trim_start().strip_prefix("= ") {
+                        Some(note) => self.push_line(&format!("  = {note}"), level, lint),
+                        None => self.push_line(line, level, lint),
+                    }
+                }
+            }
+            None => {
+                self.push_line(&header, level, lint);
+                for child in &diagnostic.children {
+                    let note = format!("  = {}: {}", child.level, child.message);
+                    self.push_line(&note, level, lint);
+                }
+            }
+        }
+        if self.rewriter.explain_lints {
+            self.explain_lint(diagnostic);
+        }
+        if self.rewriter.explain_errors {
+            self.explain_error(diagnostic);
+        }
+        self.in_diagnostic = false;
+    }
+
+    /// Explains the clippy lint of `diagnostic`, unless it has already been explained.
+    fn explain_lint(&mut self, diagnostic: &Diagnostic) {
+        // Human-readable output only mentions the lint in its notes
+        let lines = diagnostic.rendered.iter().flat_map(|s| s.lines());
+        let messages = diagnostic
+            .children
+            .iter()
+            .map(|child| child.message.as_str());
+        let name = diagnostic
+            .lint_name()
+            .into_iter()
+            .chain(lines)
+            .chain(messages)
+            .find_map(|s| clippy_lint_in(&ansi::strip(s)).map(str::to_string));
+        let Some(name) = name else {
+            return;
+        };
+        if self.explained.contains(&name) {
+            return;
+        }
+        if let Some(explanation) = explain_lint(&name, &self.rewriter.catalog) {
+            self.push_str(&explanation);
+            self.explained.push(name);
+        }
+    }
+
+    /// Explains the error code of `diagnostic`, unless it has already been explained.
+    fn explain_error(&mut self, diagnostic: &Diagnostic) {
+        let Some(code) = diagnostic.error_code() else {
+            return;
+        };
+        if self.explained.iter().any(|explained| explained == code) {
+            return;
+        }
+        if let Some(explanation) = self.rewriter.explain_error(code) {
+            self.push_str(&explanation);
+            self.explained.push(code.to_string());
+        }
+    }
+
+    fn finish(mut self) -> Vec<Part> {
+        let catalog = &self.rewriter.catalog;
+        let mut summary = Vec::new();
+        if self.no_warnings {
+            summary.push(catalog.text("no-warnings", &[]));
+        }
+        if self.hidden > 0 {
+            summary.push(catalog.plural("dismissed-hidden", self.hidden, &[]));
+        }
+        // A personality can leave out phrases by making them empty
+        for line in summary {
+            if !line.is_empty() && !self.rewriter.quiet {
+                self.push_str(&(line + "\n"));
+            }
+        }
+        self.parts
+    }
+}
+
+/// Phrases cargo's JSON records one at a time. Created by `Rewriter::message_stream()`.
+pub struct MessageStream<'a> {
+    pass: Pass<'a>,
+    /// What cargo does to each package, e.g. "Checking".
+    status: &'static str,
+    /// The packages that Clippit said the status of, with the status
+    checked_packages: Vec<(String, &'static str)>,
+    counts: Vec<DiagnosticCount>,
+    /// Whether the build finished successfully
+    is_finished: bool,
+    /// Whether cargo's "Finished" status line was said
+    said_finished: bool,
+}
+
+impl MessageStream<'_> {
+    /// Returns the text for `message`, which is empty if Clippit has nothing to say about it yet.
+    pub fn push(&mut self, message: &Message) -> Vec<Part> {
+        let package = match message {
+            Message::CompilerMessage(compiler_message) => {
+                Some((&compiler_message.package_id, self.status))
+            }
+            // `cargo doc` checks the dependencies before it documents the crates
+            Message::CompilerArtifact(artifact) if !artifact.fresh => {
+                let status = if self.status == "Documenting" && !artifact.is_documentation() {
+                    "Checking"
+                } else {
+                    self.status
+                };
+                Some((&artifact.package_id, status))
+            }
+            _ => None,
+        };
+        if let Some((package_id, status)) = package {
+            if !self
+                .checked_packages
+                .iter()
+                .any(|checked| &checked.0 == package_id && checked.1 == status)
+            {
+                self.checked_packages.push((package_id.clone(), status));
+                self.pass.push_line(
+                    &format!("{status:>12} {}", package_description(package_id)),
+                    None,
+                    None,
+                );
+            }
+        }
+
+        match message {
+            Message::CompilerMessage(compiler_message) => {
+                let level = compiler_message.message.level;
+                if matches!(level, Level::Error | Level::Warning) {
+                    let count = DiagnosticCount::find_or_insert(
+                        &mut self.counts,
+                        &compiler_message.package_id,
+                        &compiler_message.target,
+                    );
+                    if level == Level::Error {
+                        count.errors += 1;
+                    } else {
+                        count.warnings += 1;
+                    }
+                }
+
+                self.pass.push_diagnostic(&compiler_message.message);
+            }
+            Message::CompilerArtifact(artifact) => {
+                if let Some(count) = self.counts.iter_mut().find(|count| {
+                    count.package_id == artifact.package_id && count.target == artifact.target
+                }) {
+                    count.push_warnings_summary(&mut self.pass);
+                }
+            }
+            Message::BuildFinished(build_finished) => {
+                // Targets that failed have no artifact, so their warnings are summarized here
+                for count in &mut self.counts {
+                    count.push_warnings_summary(&mut self.pass);
+                    if count.errors > 0 {
+                        count.push_errors_summary(&mut self.pass);
+                    }
+                }
+                // Cargo's own "Finished" line on stderr also has the profile and the time it took
+                self.is_finished = build_finished.success;
+            }
+            Message::Unknown => {}
+        }
+
+        take(&mut self.pass.parts)
+    }
+
+    /// Returns the text for a line of stdout that is not a record, e.g. the output of the tests
+    /// run by `cargo test`.
+    pub fn push_line(&mut self, line: &str) -> Vec<Part> {
+        self.pass.push_line(line, None, None);
+        take(&mut self.pass.parts)
+    }
+
+    /// Returns the text for a line of cargo's stderr, which is empty unless the line is a status
+    /// or an error that the records do not cover, e.g. `     Running unittests src/lib.rs` or
+    /// `error: test failed, to rerun pass `--lib``.
+    pub fn push_stderr_line(&mut self, line: &str) -> Vec<Part> {
+        let plain = ansi::strip(line);
+        let status = plain.trim_start().split(' ').next().unwrap_or_default();
+        if STDERR_STATUSES.contains(&status) {
+            self.said_finished |= status == "Finished";
+            self.pass.push_line(line, None, None);
+        } else if !plain.starts_with("error: could not compile ") {
+            // Cargo's own errors, e.g. for a failed test. The summary of a crate's diagnostics is
+            // said from the records instead.
+            let level = if plain.starts_with("error:") {
+                Some(Level::Error)
+            } else if plain.starts_with("warning:") {
+                Some(Level::Warning)
+            } else {
+                None
+            };
+            if level.is_some() {
+                self.pass.no_warnings = false;
+                self.pass.push_line(line, level, None);
+            }
+        }
+        take(&mut self.pass.parts)
+    }
+
+    /// Returns the text that comes after all records, e.g. "Woohoo, no warnings!".
+    pub fn finish(mut self) -> Vec<Part> {
+        if self.is_finished && !self.said_finished {
+            self.pass.push_line("    Finished", None, None);
+        }
+        self.pass.finish()
+    }
+}
+
+/// Statuses that cargo only writes to stderr, even with `--message-format=json`.
+const STDERR_STATUSES: &[&str] = &["Finished", "Running", "Doc-tests", "Generated"];
+
+/// Returns the status that cargo writes for each package it builds with `subcommand`. For `doc`,
+/// it is only the status of the crates that are documented.
+fn status(subcommand: &str) -> &'static str {
+    match subcommand {
+        "build" | "test" => "Compiling",
+        "doc" => "Documenting",
+        _ => "Checking",
+    }
+}
+
+/// Number of warnings and errors emitted while compiling a target.
+struct DiagnosticCount {
+    package_id: String,
+    target: Target,
+    warnings: usize,
+    errors: usize,
+    /// Whether the number of warnings was said
+    is_summarized: bool,
+}
+
+impl DiagnosticCount {
+    fn find_or_insert<'a>(
+        counts: &'a mut Vec<DiagnosticCount>,
+        package_id: &str,
+        target: &Target,
+    ) -> &'a mut DiagnosticCount {
+        let index = counts
+            .iter()
+            .position(|count| count.package_id == package_id && &count.target == target)
+            .unwrap_or_else(|| {
+                counts.push(DiagnosticCount {
+                    package_id: package_id.to_string(),
+                    target: target.clone(),
+                    warnings: 0,
+                    errors: 0,
+                    is_summarized: false,
+                });
+                counts.len() - 1
+            });
+        &mut counts[index]
+    }
+
+    /// Says how many warnings the target generated, unless it was already said.
+    fn push_warnings_summary(&mut self, pass: &mut Pass) {
+        if self.warnings == 0 || self.is_summarized {
+            return;
+        }
+        self.is_summarized = true;
+        pass.push_line(
+            &format!(
+                "warning: `{}` ({}) generated {} warning{}",
+                package_name(&self.package_id),
+                self.target.description(),
+                self.warnings,
+                plural(self.warnings),
+            ),
+            Some(Level::Warning),
+            None,
+        );
+    }
+
+    /// Says that the target could not be compiled because of its errors.
+    fn push_errors_summary(&self, pass: &mut Pass) {
+        let mut line = format!(
+            "error: could not compile `{}` ({}) due to {} previous error{}",
+            package_name(&self.package_id),
+            self.target.description(),
+            self.errors,
+            plural(self.errors),
+        );
+        if self.warnings > 0 {
+            line.push_str(&format!(
+                "; {} warning{} emitted",
+                self.warnings,
+                plural(self.warnings)
+            ));
+        }
+        pass.push_line(&line, Some(Level::Error), None);
+    }
+}
+
+/// Returns the texts that lines must start with for an anchored pattern to match them, e.g.
+/// `"warning: "` and `"error: "` for `^(?:warning|error): (.*)`.
+fn literal_prefixes(pattern: &str) -> Option<Vec<Vec<u8>>> {
+    let hir = regex_syntax::parse(pattern).ok()?;
+    if !hir.properties().look_set_prefix().contains(Look::Start) {
+        return None;
+    }
+    let literals = Extractor::new()
+        .kind(ExtractKind::Prefix)
+        .extract(&hir)
+        .literals()?
+        .iter()
+        .map(|literal| literal.as_bytes().to_vec())
+        .collect();
+    Some(literals)
+}
+
+/// A SplitMix64 random number generator, which is reproducible from its seed.
+struct Random(AtomicU64);
+
+impl Random {
+    fn next(&self) -> u64 {
+        const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut z = self
+            .0
+            .fetch_add(GAMMA, Ordering::Relaxed)
+            .wrapping_add(GAMMA);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{merge_rules, parse_rules};
+    use rstest::rstest;
+
+    #[test]
+    fn test_custom_rules() {
+        let mut rules = builtin_rules();
+        merge_rules(
+            &mut rules,
+            parse_rules(
+                r#"
+[[rules]]
+id = "approx-constant"
+pattern = '^error: approximate value of `(.*)` found'
+replacement = "Did you mean `$1`? I can't tell."
+lint = "clippy::approx_constant"
+priority = 1
+
+[[rules]]
+id = "hint"
+enabled = false
+"#,
+            )
+            .unwrap(),
+        );
+        let rewriter = Rewriter::new(rules).unwrap();
+
+        let input = r#"error: approximate value of `f{32, 64}::consts::PI` found
+ --> src/main.rs:6:14
+  |
+6 |     let pi = 3.14;
+  |              ^^^^
+  |
+  = help: consider using the constant directly
+  = note: `#[deny(clippy::approx_constant)]` on by default
+error: approximate value of `f{32, 64}::consts::PI` found
+"#;
+        assert_eq!(
+            rewriter.replace_words(input),
+            r#"Did you mean `f{32, 64}::consts::PI`? I can't tell.
+ --> src/main.rs:6:14
+  |
+6 |     let pi = 3.14;
+  |              ^^^^
+  |
+  = help: consider using the constant directly
+  Note: `#[deny(clippy::approx_constant)]` on by default.
+Hmmm... approximate value of `f{32, 64}::consts::PI` found.
+"#
+        );
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let mut rules = builtin_rules();
+        rules[0].pattern = "(".to_string();
+        assert!(Rewriter::new(rules.clone()).is_err());
+
+        rules[0].pattern = String::new();
+        assert!(Rewriter::new(rules).is_err());
+    }
+
+    #[test]
+    fn test_message_stream_test() {
+        let rewriter = Rewriter::default();
+        let mut stream = rewriter.message_stream("test");
+        let mut output = String::new();
+        for message in parse_messages(
+            r#"{"reason":"compiler-artifact","package_id":"path+file:///playground#0.0.1","target":{"kind":["lib"],"name":"playground"},"fresh":false}
+{"reason":"build-finished","success":true}"#,
+        ) {
+            output.push_str(&concat(&stream.push(&message)));
+        }
+        output.push_str(&concat(
+            &stream.push_stderr_line("    Finished `test` profile"),
+        ));
+        output.push_str(&concat(&stream.push_stderr_line(
+            "     Running unittests src/lib.rs (target/debug/deps/playground-1f2c3d4e)",
+        )));
+        output.push_str(&concat(
+            &stream.push_line("test result: ok. 1 passed; 0 failed"),
+        ));
+        output.push_str(&concat(&stream.finish()));
+
+        assert_eq!(
+            output,
+            r#"I'm compiling playground v0.0.1 (/playground)...
+I finished compiling `test` profile.
+I'm running unittests src/lib.rs (target/debug/deps/playground-1f2c3d4e)...
+test result: ok. 1 passed; 0 failed
+Woohoo, no warnings!
+"#
+        );
+    }
+
+    #[test]
+    fn test_message_stream_doc() {
+        let rewriter = Rewriter::default();
+        let mut stream = rewriter.message_stream("doc");
+        let mut output = String::new();
+        for message in parse_messages(
+            r#"{"reason":"compiler-artifact","package_id":"registry+https://github.com/rust-lang/crates.io-index#serde@1.0.188","target":{"kind":["lib"],"name":"serde"},"filenames":["/playground/target/debug/deps/libserde-1f2c3d4e.rmeta"],"fresh":false}
+{"reason":"compiler-artifact","package_id":"path+file:///playground#0.0.1","target":{"kind":["lib"],"name":"playground"},"filenames":["/playground/target/doc/playground/index.html"],"fresh":false}
+{"reason":"build-finished","success":true}"#,
+        ) {
+            output.push_str(&concat(&stream.push(&message)));
+        }
+
+        // Dependencies are only checked
+        assert_eq!(
+            output,
+            "I'm checking serde v1.0.188...
+I'm writing the documentation for playground v0.0.1 (/playground)...
+"
+        );
+    }
+
+    #[test]
+    fn test_message_stream_failed() {
+        let rewriter = Rewriter::default();
+        let mut stream = rewriter.message_stream("clippy");
+        let diagnostic = |level: &str| {
+            format!(
+                r#"{{"reason":"compiler-message","package_id":"path+file:///playground#0.0.1","target":{{"kind":["bin"],"name":"playground"}},"message":{{"rendered":null,"children":[],"level":"{level}","message":"unused variable: `a`","spans":[],"code":null}}}}"#
+            )
+        };
+        let input = [
+            diagnostic("warning"),
+            diagnostic("warning"),
+            diagnostic("error"),
+            r#"{"reason":"build-finished","success":false}"#.to_string(),
+        ];
+        let mut output = String::new();
+        for message in parse_messages(&input.join("\n")) {
+            output.push_str(&concat(&stream.push(&message)));
+        }
+        output.push_str(&concat(&stream.finish()));
+
+        // The crate failed, so there is no artifact to say the number of warnings after
+        assert!(output.ends_with(
+            r#"Hmmm... `playground` (bin "playground") generated 2 warnings.
+Let's fix `playground` (bin "playground")!
+"#
+        ));
+        assert!(!output.contains("I finished compiling"));
+    }
+
+    #[test]
+    fn test_parts() {
+        let input = "    Checking playground v0.0.1 (/playground)
+warning: unused variable: `a`
+ --> src/main.rs:2:9
+
+warning: `playground` (bin \"playground\") generated 1 warning
+";
+        assert_eq!(
+            Rewriter::default().replace_output_parts(input),
+            [
+                Part::Text("I'm checking playground v0.0.1 (/playground)...\n".to_string()),
+                Part::Diagnostic(
+                    "Hmmm... unused variable: `a`.\n --> src/main.rs:2:9\n".to_string()
+                ),
+                // The summary is not a diagnostic of its own
+                Part::Text(
+                    "\nHmmm... `playground` (bin \"playground\") generated 1 warning.\n"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dismissed() {
+        let input = "warning: unused variable: `a`
+ --> src/main.rs:2:9
+  |
+  = note: `#[warn(unused_variables)]` on by default
+
+warning: unnecessary parentheses
+ --> src/main.rs:3:13
+  |
+  = help: for further information visit https://rust-lang.github.io/rust-clippy/master/index.html#double_parens
+  = note: `#[warn(clippy::double_parens)]` on by default
+
+warning: `playground` (bin \"playground\") generated 2 warnings
+";
+        let mut dismissed = Dismissed::default();
+        dismissed.insert("clippy::double_parens");
+        let mut rewriter = Rewriter::default();
+        rewriter.set_dismissed(dismissed);
+
+        let output = rewriter.replace_words(input);
+        assert!(output.contains("Hmmm... unused variable: `a`."));
+        assert!(!output.contains("parentheses"));
+        // The dismissed warning still counts
+        assert!(output.contains("generated 2 warnings."));
+        assert!(output.ends_with("I didn't show you 1 tip that you dismissed.\n"));
+    }
+
+    #[test]
+    fn test_explain_lints() {
+        let warning = "warning: unnecessary parentheses
+ --> src/main.rs:3:13
+  |
+  = help: for further information visit https://rust-lang.github.io/rust-clippy/master/index.html#double_parens
+  = note: `#[warn(clippy::double_parens)]` on by default
+
+";
+        let mut rewriter = Rewriter::default();
+        rewriter.set_explain_lints(true);
+        let output = rewriter.replace_words(&warning.repeat(2));
+        assert_eq!(
+            output
+                .matches("Did you know? `clippy::double_parens` checks for")
+                .count(),
+            1
+        );
+
+        let parts = rewriter.replace_output_parts(warning);
+        assert!(matches!(&parts[0], Part::Diagnostic(text) if text.contains("Did you know?")));
+    }
+
+    #[test]
+    fn test_explain_errors() {
+        let error = "error[E0597]: `y` does not live long enough
+ --> src/main.rs:4:9
+  |
+
+";
+        let mut rewriter = Rewriter::default();
+        rewriter.set_explain_errors(true);
+        rewriter.set_error_explainer(ErrorExplainer::with_lookup(|code| {
+            Some(format!("{code} is a test.\n"))
+        }));
+        let output = rewriter.replace_words(&error.repeat(2));
+        assert_eq!(
+            output
+                .matches("Here's what E0597 means: E0597 is a test.\n")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_catalog() {
+        let mut rules = builtin_rules();
+        merge_rules(
+            &mut rules,
+            parse_rules(
+                r#"
+[[rules]]
+id = "checking"
+pattern = '^    Checking(.*)'
+replacement = "Looking at$1"
+"#,
+            )
+            .unwrap(),
+        );
+        let mut rewriter = Rewriter::new(rules).unwrap();
+        rewriter.set_catalog(Catalog::new("ja"));
+
+        let input = "    Checking playground v0.0.1 (/playground)
+warning: unused variable: `a`
+";
+        // The user's own replacement is not translated
+        assert_eq!(
+            rewriter.replace_words(input),
+            "Looking at playground v0.0.1 (/playground)\nうーん... unused variable: `a`。\n"
+        );
+        assert_eq!(
+            rewriter.replace_words("    Finished dev"),
+            "コンパイルが終わりました dev。\nやったー、警告はありません！\n"
+        );
+
+        rewriter.set_catalog(Catalog::new("de"));
+        assert!(rewriter
+            .replace_words(input)
+            .ends_with("Hmmm... unused variable: `a`.\n"));
+        rewriter.set_catalog(Catalog::default());
+        assert!(rewriter
+            .replace_words(input)
+            .ends_with("Hmmm... unused variable: `a`.\n"));
+        assert_eq!(
+            rewriter.replace_words("    Finished dev"),
+            "I finished compiling dev.\nWoohoo, no warnings!\n"
+        );
+    }
+
+    #[test]
+    fn test_seed() {
+        let input = "warning: unused variable: `a`\n".repeat(20);
+        let say = |seed: Option<u64>| {
+            let mut rewriter = Rewriter::default();
+            if let Some(seed) = seed {
+                rewriter.set_seed(seed);
+            }
+            rewriter.replace_words(&input)
+        };
+
+        // Without a seed, the rule always says its replacement
+        assert_eq!(say(None).matches("Hmmm... unused variable").count(), 20);
+        // The same seed picks the same phrasings
+        assert_eq!(say(Some(1)), say(Some(1)));
+        assert_ne!(say(Some(1)), say(Some(2)));
+        let rule = builtin_rules()
+            .into_iter()
+            .find(|rule| rule.id == "warning")
+            .unwrap();
+        for phrasing in [&rule.replacement].into_iter().chain(&rule.alternatives) {
+            let phrasing = phrasing.replace("$1", "unused variable: `a`");
+            assert!(say(Some(1)).contains(&phrasing), "{phrasing}");
+        }
+
+        // Translated rules have no alternatives
+        let mut rewriter = Rewriter::default();
+        rewriter.set_seed(1);
+        rewriter.set_catalog(Catalog::new("de"));
+        assert_eq!(
+            rewriter
+                .replace_words(&input)
+                .matches("Hmmm... unused variable")
+                .count(),
+            20
+        );
+    }
+
+    #[test]
+    fn test_seed_per_diagnostic() {
+        let mut rules = builtin_rules();
+        merge_rules(
+            &mut rules,
+            parse_rules(
+                r#"
+[[rules]]
+id = "warning"
+pattern = '^warning: (.*)'
+replacement = "0 $1"
+alternatives = ["1 $1", "2 $1"]
+
+[[rules]]
+id = "hint"
+pattern = '^  = help: (.*)'
+replacement = "  0 $1"
+alternatives = ["  1 $1", "  2 $1"]
+"#,
+            )
+            .unwrap(),
+        );
+        let input = "warning: unused variable: `a`\n  = help: remove it\n\n".repeat(10);
+        for seed in 0..10 {
+            let mut rewriter = Rewriter::new(rules.clone()).unwrap();
+            rewriter.set_seed(seed);
+            let output = rewriter.replace_words(&input);
+            // The header and the hint of each diagnostic are phrased with the same pick
+            for diagnostic in output.split("\n\n").filter(|s| !s.is_empty()) {
+                let mut lines = diagnostic.lines();
+                let header = lines.next().unwrap();
+                let hint = lines.next().unwrap();
+                assert_eq!(header[..1], hint.trim_start()[..1], "{diagnostic}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_colors() {
+        let input = "\x1b[1m\x1b[32m   Compiling\x1b[0m playground v0.0.1 (/playground)
+\x1b[0m\x1b[1m\x1b[33mwarning\x1b[0m\x1b[0m\x1b[1m: unused variable: `pi`\x1b[0m
+\x1b[0m \x1b[0m\x1b[0m\x1b[1m\x1b[38;5;12m--> \x1b[0m\x1b[0msrc/main.rs:5:9\x1b[0m
+";
+        assert_eq!(
+            Rewriter::default().replace_words(input),
+            "\x1b[1m\x1b[32mI'm compiling playground v0.0.1 (/playground)...\x1b[0m
+\x1b[1m\x1b[33mHmmm... unused variable: `pi`.\x1b[0m
+\x1b[0m \x1b[0m\x1b[0m\x1b[1m\x1b[38;5;12m--> \x1b[0m\x1b[0msrc/main.rs:5:9\x1b[0m
+"
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "error: test failed, to rerun pass `--lib`",
+        "Hmmm... test failed, to rerun pass `--lib`.\n"
+    )]
+    #[case(
+        "\x1b[1m\x1b[91merror\x1b[0m\x1b[1m:\x1b[0m test failed, to rerun pass `--bin playground`",
+        "\x1b[1m\x1b[91mHmmm... test failed, to rerun pass `--bin playground`.\x1b[0m\n"
+    )]
+    #[case(
+        "error: no library targets found in package `playground`",
+        "Hmmm... no library targets found in package `playground`.\n"
+    )]
+    #[case(
+        "warning: unused manifest key: package.colour",
+        "It looks like this could be improved because unused manifest key: package.colour.\n"
+    )]
+    #[case(
+        "error: could not compile `playground` (lib) due to 1 previous error",
+        ""
+    )]
+    #[case("   Compiling playground v0.0.1 (/playground)", "")]
+    fn test_message_stream_stderr(#[case] line: &str, #[case] expected: &str) {
+        let rewriter = Rewriter::default();
+        let mut stream = rewriter.message_stream("test");
+        assert_eq!(concat(&stream.push_stderr_line(line)), expected);
+    }
+
+    #[rstest]
+    #[case("^    Checking(.*)",Some(vec!["    Checking"]))]
+    #[case("^(?:warning|error): (.*)", Some(vec!["warning: ", "error: "]))]
+    #[case("^(?:(  )= )?note: (.*)", Some(vec!["  = note: ", "note: "]))]
+    #[case("thread 'main' panicked at ", None)]
+    fn test_literal_prefixes(#[case] pattern: &str, #[case] expected: Option<Vec<&str>>) {
+        let expected = expected.map(|prefixes| {
+            prefixes
+                .into_iter()
+                .map(|p| p.as_bytes().to_vec())
+                .collect()
+        });
+        assert_eq!(literal_prefixes(pattern), expected);
+    }
+}
@@ -0,0 +1,181 @@
+//! Rules that rewrite lines of cargo's output to sound like Clippit.
+//!
+//! The built-in rules are in `src/rules.toml`, which also describes the format. Rules can be added
+//! or replaced in `~/.config/clippit/rules.toml` and `./clippit.toml`. A rule replaces the rule
+//! with the same `id` from an earlier file.
+
+use crate::diagnostic::Level;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+const BUILTIN_RULES: &str = include_str!("rules.toml");
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Rule {
+    pub id: String,
+    /// Can be left out if the rule only turns off an earlier rule with `enabled = false`.
+    #[serde(default)]
+    pub pattern: String,
+    #[serde(default)]
+    pub replacement: String,
+    /// Other replacements, one of which is picked at random instead of `replacement` when the
+    /// `Rewriter` has a seed.
+    #[serde(default)]
+    pub alternatives: Vec<String>,
+    /// Only the first matching line is rewritten if `true`.
+    #[serde(default)]
+    pub once: bool,
+    /// Rules with a higher priority are tried first.
+    #[serde(default)]
+    pub priority: i32,
+    /// Only lines of diagnostics with this level are rewritten.
+    #[serde(default)]
+    pub level: Option<Level>,
+    /// Only lines of diagnostics from this lint are rewritten. A trailing `*` matches any suffix.
+    #[serde(default)]
+    pub lint: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Rule {
+    /// Returns `true` if the rule's conditions allow it to rewrite a line of a diagnostic with the
+    /// given level and lint. Lines outside of diagnostics have neither.
+    pub fn applies_to(&self, level: Option<Level>, lint: Option<&str>) -> bool {
+        let level_matches = self.level.is_none() || self.level == level;
+        let lint_matches = match (&self.lint, lint) {
+            (None, _) => true,
+            (Some(pattern), Some(lint)) => match pattern.strip_suffix('*') {
+                Some(prefix) => lint.starts_with(prefix),
+                None => pattern == lint,
+            },
+            (Some(_), None) => false,
+        };
+        level_matches && lint_matches
+    }
+}
+
+#[derive(Deserialize)]
+struct RuleFile {
+    // Other settings may be in the same file
+    #[serde(default)]
+    rules: Vec<Rule>,
+}
+
+/// Parses the `[[rules]]` tables of a TOML document.
+pub fn parse_rules(s: &str) -> Result<Vec<Rule>> {
+    Ok(toml::from_str::<RuleFile>(s)?.rules)
+}
+
+/// Returns the rules that clippit uses if there are no rule files.
+pub fn builtin_rules() -> Vec<Rule> {
+    parse_rules(BUILTIN_RULES).unwrap()
+}
+
+/// Returns the paths of the rule files that are loaded after the built-in rules, in order.
+/// `config` replaces `./clippit.toml`.
+pub fn rule_file_paths(config: Option<&Path>) -> Vec<PathBuf> {
+    let mut result = Vec::new();
+    if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        result.push(PathBuf::from(home).join(".config/clippit/rules.toml"));
+    }
+    result.push(config.map_or_else(|| PathBuf::from("clippit.toml"), Path::to_path_buf));
+    result
+}
+
+/// Returns the built-in rules, overridden by the rules in the files from `rule_file_paths()`.
+/// The default files are skipped if they do not exist, but `config` has to exist.
+pub fn load_rules(config: Option<&Path>) -> Result<Vec<Rule>> {
+    let mut rules = builtin_rules();
+    for path in rule_file_paths(config) {
+        if path.exists() || config == Some(path.as_path()) {
+            merge_rules(&mut rules, load_rule_file(&path)?);
+        }
+    }
+    Ok(rules)
+}
+
+pub fn load_rule_file(path: &Path) -> Result<Vec<Rule>> {
+    let s = std::fs::read_to_string(path)
+        .with_context(|| format!("could not read {}", path.display()))?;
+    parse_rules(&s).with_context(|| format!("invalid rule file {}", path.display()))
+}
+
+/// Returns the string setting `name`, e.g. `character = "rover"`, from the rule files from
+/// `rule_file_paths()`. A later file overrides an earlier one.
+pub fn configured_setting(config: Option<&Path>, name: &str) -> Result<Option<String>> {
+    let mut result = None;
+    for path in rule_file_paths(config) {
+        if !path.exists() {
+            continue;
+        }
+        let s = std::fs::read_to_string(&path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        let settings: toml::Table =
+            toml::from_str(&s).with_context(|| format!("invalid rule file {}", path.display()))?;
+        match settings.get(name) {
+            Some(toml::Value::String(value)) => result = Some(value.clone()),
+            Some(_) => anyhow::bail!("{name} in {} is not a string", path.display()),
+            None => {}
+        }
+    }
+    Ok(result)
+}
+
+/// Adds `overrides` to `rules`. Rules with an existing id replace the old rule in place.
+pub fn merge_rules(rules: &mut Vec<Rule>, overrides: Vec<Rule>) {
+    for rule in overrides {
+        match rules.iter_mut().find(|r| r.id == rule.id) {
+            Some(r) => *r = rule,
+            None => rules.push(rule),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_rules() {
+        let mut rules = builtin_rules();
+        let count = rules.len();
+        merge_rules(
+            &mut rules,
+            parse_rules(
+                r#"
+[output]
+width = 80
+
+[[rules]]
+id = "warning"
+pattern = '^warning: (.*)'
+replacement = "Heads up: $1."
+
+[[rules]]
+id = "unused-variable"
+pattern = '^warning: unused variable: (.*)'
+replacement = "You forgot about $1."
+lint = "unused_*"
+priority = 1
+"#,
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(rules.len(), count + 1);
+        let warning = rules.iter().find(|rule| rule.id == "warning").unwrap();
+        assert_eq!(warning.replacement, "Heads up: $1.");
+        assert_eq!(warning.priority, 0);
+
+        let unused_variable = rules.last().unwrap();
+        assert!(unused_variable.applies_to(Some(Level::Warning), Some("unused_variables")));
+        assert!(!unused_variable.applies_to(Some(Level::Warning), Some("clippy::unused_io")));
+        assert!(!unused_variable.applies_to(None, None));
+    }
+}
@@ -0,0 +1,32 @@
+//! The cargo workspace that clippit runs in.
+//!
+//! Rustc's file names and clippit's own files are relative to the workspace root, so that clippit
+//! finds them from any directory of the workspace.
+
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::process::Command;
+
+#[derive(Deserialize)]
+struct Metadata {
+    workspace_root: PathBuf,
+}
+
+/// Returns the root directory of the workspace of the current directory from `cargo metadata`,
+/// or the current directory if it is not in a workspace.
+pub fn workspace_root() -> PathBuf {
+    let output = Command::new("cargo")
+        .args([
+            "metadata",
+            "--format-version",
+            "1",
+            "--no-deps",
+            "--offline",
+        ])
+        .output();
+    output
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| serde_json::from_slice::<Metadata>(&output.stdout).ok())
+        .map_or_else(|| PathBuf::from("."), |metadata| metadata.workspace_root)
+}